@@ -0,0 +1,349 @@
+use crate::evaluation::{bidding_position, HandEvaluator};
+use crate::ismcts::Action;
+use crate::pimc::PimcAgent;
+use crate::solver::Solver;
+use schafkopf_lib::schafkopf_env::game_logic::{Auction, AuctionAction, Card, Contract, Game};
+use std::fmt;
+use std::fmt::Formatter;
+
+/// How the card play of a finished game is judged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Judge {
+    /// Solves the actual deal with all hands open. Exact, but blames players for
+    /// not knowing cards they couldn't see.
+    DoubleDummy,
+    /// Searches like a PIMC agent from what the player knew at that point.
+    Sampled { samples: usize, seed: u64 },
+}
+
+/// A decision of one player and how it compares to the best alternative.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub player: usize,
+    /// Position of the decision in the game's `played` cards or the auction's history.
+    pub index: usize,
+    pub action: Action,
+    pub value: f64,
+    pub best: Action,
+    pub best_value: f64,
+    /// Value of the worst alternative, the loss is measured against the spread.
+    pub worst_value: f64,
+}
+
+impl Decision {
+    /// What the choice gave away compared to the best alternative.
+    pub fn loss(&self) -> f64 {
+        self.best_value - self.value
+    }
+
+    /// 1 for the best alternative down to 0 for the worst one.
+    pub fn accuracy(&self) -> f64 {
+        let spread = self.best_value - self.worst_value;
+        if spread <= 0.0 {
+            1.0
+        } else {
+            1.0 - self.loss() / spread
+        }
+    }
+}
+
+/// The decisions of one player.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerReport {
+    /// Card plays where the choice mattered, valued in card points of the player's side.
+    pub plays: Vec<Decision>,
+    /// Intents and bids, valued by the hand evaluator in the rule set's tariffs.
+    pub bids: Vec<Decision>,
+}
+
+impl PlayerReport {
+    /// Average accuracy of the card play in percent.
+    pub fn accuracy(&self) -> f64 {
+        if self.plays.is_empty() {
+            return 100.0;
+        }
+        100.0 * self.plays.iter().map(|d| d.accuracy()).sum::<f64>() / self.plays.len() as f64
+    }
+
+    /// Card plays that lost points, the most expensive first.
+    pub fn mistakes(&self) -> Vec<&Decision> {
+        let mut mistakes = self
+            .plays
+            .iter()
+            .filter(|d| d.loss() > 0.0)
+            .collect::<Vec<&Decision>>();
+        mistakes.sort_by(|a, b| b.loss().total_cmp(&a.loss()));
+        mistakes
+    }
+}
+
+/// Review of a finished game, decision by decision.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub players: [PlayerReport; 4],
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (player, report) in self.players.iter().enumerate() {
+            writeln!(f, "Player {}: accuracy {:.1}%", player, report.accuracy())?;
+            for bid in report.bids.iter().filter(|d| d.loss() > 0.0) {
+                writeln!(
+                    f,
+                    "  auction: {} instead of {} ({:+.1})",
+                    describe(&bid.action),
+                    describe(&bid.best),
+                    -bid.loss()
+                )?;
+            }
+            for play in report.mistakes() {
+                writeln!(
+                    f,
+                    "  trick {}: {} instead of {} ({:+.1} points)",
+                    play.index / 4 + 1,
+                    describe(&play.action),
+                    describe(&play.best),
+                    -play.loss()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe(action: &Action) -> String {
+    match action {
+        Action::Intent(true) => "announced to play".to_string(),
+        Action::Intent(false) => "didn't announce".to_string(),
+        Action::Bid(contract) => format!("bid {}", contract),
+        Action::Play(card) => format!("played {}", card),
+    }
+}
+
+/// Reviews finished games the way a chess engine reviews a game: every decision
+/// is compared to the best alternative at that point.
+pub struct Analyzer {
+    pub judge: Judge,
+    evaluator: HandEvaluator,
+    solver: Solver,
+}
+
+impl Analyzer {
+    pub fn new(judge: Judge) -> Analyzer {
+        Analyzer {
+            judge,
+            evaluator: HandEvaluator::default(),
+            solver: Solver::new(),
+        }
+    }
+
+    pub fn analyze(&mut self, auction: &Auction, game: &Game) -> Report {
+        let start = initial_position(game);
+        let mut report = Report::default();
+        self.analyze_auction(auction, &start, &mut report);
+        self.analyze_play(auction, game, &start, &mut report);
+        report
+    }
+
+    fn analyze_auction(&self, auction: &Auction, start: &Game, report: &mut Report) {
+        let mut replay = Auction::new(start.forehand);
+        for (index, (player, action)) in auction.history.iter().enumerate() {
+            let state = start.get_player_game_state(*player);
+            let position = bidding_position(&state);
+            let options = match action {
+                AuctionAction::Intent(intent) => {
+                    let best = self
+                        .evaluator
+                        .evaluate_all(state.hand, position)
+                        .first()
+                        .map_or(0.0, |e| e.expected_value);
+                    replay.announce_intent(*intent);
+                    vec![(Action::Intent(true), best), (Action::Intent(false), 0.0)]
+                }
+                AuctionAction::Bid(bid) => {
+                    let options = replay
+                        .valid_bids(Some(state.hand))
+                        .into_iter()
+                        .map(|contract| {
+                            let value = self
+                                .evaluator
+                                .evaluate(state.hand, contract, position)
+                                .expected_value;
+                            (Action::Bid(contract), value)
+                        })
+                        .collect();
+                    replay.bid(*bid);
+                    options
+                }
+            };
+            let chosen = match action {
+                AuctionAction::Intent(intent) => Action::Intent(*intent),
+                AuctionAction::Bid(bid) => Action::Bid(*bid),
+            };
+            if let Some(decision) = decide(*player, index, chosen, &options) {
+                report.players[*player].bids.push(decision);
+            }
+        }
+    }
+
+    fn analyze_play(&mut self, auction: &Auction, game: &Game, start: &Game, report: &mut Report) {
+        let mut pimc = match self.judge {
+            Judge::Sampled { samples, seed } => {
+                let mut pimc = PimcAgent::with_seed(samples, seed);
+                pimc.set_auction(Some(auction.clone()));
+                Some(pimc)
+            }
+            Judge::DoubleDummy => None,
+        };
+        let mut replay = start.clone();
+        for (index, played) in game.played.iter().enumerate() {
+            let player = played.1;
+            let options = match pimc.as_mut() {
+                Some(pimc) => pimc.card_values(&replay.get_player_game_state(player)),
+                None => self.double_dummy(&replay, player),
+            };
+            if options.len() > 1 {
+                let options = options
+                    .into_iter()
+                    .map(|(card, value)| (Action::Play(card), value))
+                    .collect::<Vec<(Action, f64)>>();
+                if let Some(decision) = decide(player, index, Action::Play(played.0), &options) {
+                    report.players[player].plays.push(decision);
+                }
+            }
+            replay.play_card(played.0);
+        }
+    }
+
+    /// Card points of the player's side for every legal card, with all hands open.
+    fn double_dummy(&mut self, game: &Game, player: usize) -> Vec<(Card, f64)> {
+        let (result, own_side) = match game.contract {
+            Contract::Ramsch => (self.solver.solve_ramsch(game, player), false),
+            _ => {
+                let team = game.declaring_team();
+                (self.solver.solve(game, team), team[player])
+            }
+        };
+        result
+            .card_values
+            .into_iter()
+            .map(|(card, value)| {
+                let value = if own_side { value } else { 120 - value };
+                (card, value as f64)
+            })
+            .collect()
+    }
+}
+
+/// Compares the chosen action with the alternatives. Decisions where every
+/// alternative is worth the same aren't decisions at all.
+fn decide(
+    player: usize,
+    index: usize,
+    action: Action,
+    options: &[(Action, f64)],
+) -> Option<Decision> {
+    let value = options.iter().find(|o| o.0 == action)?.1;
+    let (best, best_value) = *options.iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let worst_value = options.iter().map(|o| o.1).fold(f64::INFINITY, f64::min);
+    if best_value <= worst_value {
+        return None;
+    }
+    Some(Decision {
+        player,
+        index,
+        action,
+        value,
+        best: if value >= best_value { action } else { best },
+        best_value,
+        worst_value,
+    })
+}
+
+/// The game as it was dealt, before any card was played.
+fn initial_position(game: &Game) -> Game {
+    let mut hands: [Vec<Card>; 4] = Default::default();
+    for played in game.played.iter() {
+        hands[played.1].push(played.0);
+    }
+    for (hand, remaining) in hands.iter_mut().zip(game.hands.iter()) {
+        hand.extend(remaining.cards.iter());
+    }
+    let mut start = Game::from_hands(game.forehand, hands);
+    start.contract = game.contract;
+    start.declarer = game.declarer;
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::HeuristicAgent;
+    use crate::testing::seeded_game;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use schafkopf_lib::schafkopf_env::agent::Agent;
+    use schafkopf_lib::schafkopf_env::game_logic::Suit;
+
+    #[test]
+    fn test_perfect_play_is_accurate() {
+        let mut game = seeded_game(0, 1);
+        game.contract = Contract::Solo(Suit::Acorns);
+        let mut analyzer = Analyzer::new(Judge::DoubleDummy);
+        // the first tricks are played at random, the rest perfectly
+        let mut rng = StdRng::seed_from_u64(3);
+        while game.played.len() < 16 {
+            let legal = game.get_legal_actions(&game.hands[game.next_player]);
+            let card = **legal.choose(&mut rng).unwrap();
+            game.play_card(card);
+        }
+        let mut perfect = game.clone();
+        while !perfect.is_over() {
+            let player = perfect.next_player;
+            let best = analyzer
+                .double_dummy(&perfect, player)
+                .into_iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            perfect.play_card(best.0);
+        }
+        let report = analyzer.analyze(&Auction::new(0), &perfect);
+        for player in report.players.iter() {
+            for play in player.plays.iter().filter(|d| d.index >= 16) {
+                assert_eq!(play.loss(), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_is_consistent() {
+        let mut game = seeded_game(1, 1);
+        game.contract = Contract::Call(Suit::Leaves);
+        game.declarer = 1;
+        let mut agent = HeuristicAgent::new();
+        while !game.is_over() {
+            let player = game.next_player;
+            let state = game.get_player_game_state(player);
+            let legal = game.get_legal_actions(&game.hands[player]);
+            // player 2 plays the first legal card, everybody else thinks
+            let card = if player == 2 {
+                *legal[0]
+            } else {
+                agent.get_play(&state, legal)
+            };
+            game.play_card(card);
+        }
+        let report = Analyzer::new(Judge::DoubleDummy).analyze(&Auction::new(1), &game);
+        for player in report.players.iter() {
+            assert!((0.0..=100.0).contains(&player.accuracy()));
+            let losses = player
+                .mistakes()
+                .iter()
+                .map(|d| d.loss())
+                .collect::<Vec<f64>>();
+            assert!(losses.windows(2).all(|w| w[0] >= w[1]));
+        }
+        assert!(report.to_string().contains("Player 2: accuracy"));
+    }
+}
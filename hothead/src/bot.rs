@@ -0,0 +1,199 @@
+use crate::heuristic::HeuristicAgent;
+use crate::ismcts::{Budget, IsmctsAgent, IsmctsConfig, RolloutPolicy};
+use crate::pimc::PimcAgent;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{Auction, Card, Contract, Game, PlayerGameState};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Playing strength offered to human players.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Level {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Level, String> {
+        match s {
+            "beginner" => Ok(Level::Beginner),
+            "intermediate" => Ok(Level::Intermediate),
+            "expert" => Ok(Level::Expert),
+            other => Err(format!(
+                "Unknown level {}, use beginner, intermediate or expert.",
+                other
+            )),
+        }
+    }
+}
+
+/// Which agent makes the decisions, and with how much search.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Search {
+    /// Rules of thumb only, no search at all.
+    Heuristic,
+    /// ISMCTS with random or heuristic rollouts.
+    Ismcts {
+        iterations: usize,
+        rollout: RolloutPolicy,
+    },
+    /// PIMC over the double-dummy solver.
+    Pimc { samples: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BotConfig {
+    pub level: Level,
+    pub search: Search,
+    /// Chance of playing a random legal card instead of the chosen one.
+    pub noise: f64,
+    /// Every decision returns within this time, whatever the search budget says.
+    pub time_limit: Duration,
+}
+
+impl BotConfig {
+    pub fn new(level: Level) -> BotConfig {
+        let (search, noise) = match level {
+            Level::Beginner => (Search::Heuristic, 0.2),
+            Level::Intermediate => (
+                Search::Ismcts {
+                    iterations: 300,
+                    rollout: RolloutPolicy::Heuristic,
+                },
+                0.05,
+            ),
+            Level::Expert => (Search::Pimc { samples: 24 }, 0.0),
+        };
+        BotConfig {
+            level,
+            search,
+            noise,
+            time_limit: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> BotConfig {
+        self.time_limit = time_limit;
+        self
+    }
+}
+
+/// An agent of a configurable strength, e.g. an opponent picked in the GUI.
+pub struct Bot {
+    pub config: BotConfig,
+    agent: Box<dyn Agent>,
+    rng: StdRng,
+}
+
+impl Bot {
+    pub fn new(config: BotConfig) -> Bot {
+        Bot::with_rng(config, StdRng::from_entropy())
+    }
+
+    pub fn with_seed(config: BotConfig, seed: u64) -> Bot {
+        Bot::with_rng(config, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(config: BotConfig, mut rng: StdRng) -> Bot {
+        // leave some room for everything around the search itself
+        let limit = config.time_limit.mul_f64(0.8);
+        let agent: Box<dyn Agent> = match config.search {
+            Search::Heuristic => Box::new(HeuristicAgent::new()),
+            Search::Ismcts {
+                iterations,
+                rollout,
+            } => {
+                let config = IsmctsConfig {
+                    budget: Budget::Iterations(iterations),
+                    rollout,
+                    time_limit: Some(limit),
+                    ..IsmctsConfig::default()
+                };
+                Box::new(IsmctsAgent::with_seed(config, rng.gen()))
+            }
+            Search::Pimc { samples } => {
+                let mut agent = PimcAgent::with_seed(samples, rng.gen());
+                agent.time_limit = Some(limit);
+                Box::new(agent)
+            }
+        };
+        Bot { config, agent, rng }
+    }
+}
+
+impl Agent for Bot {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        self.agent.get_intent(state, auction)
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        self.agent.get_bid(state, auction)
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        if self.rng.gen_bool(self.config.noise.clamp(0.0, 1.0)) {
+            return **legal_plays.choose(&mut self.rng).unwrap();
+        }
+        self.agent.get_play(state, legal_plays)
+    }
+
+    fn game_over(&mut self, player_nr: usize, auction: &Auction, game: &Game) {
+        self.agent.game_over(player_nr, auction, game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_parses_levels() {
+        assert_eq!("beginner".parse(), Ok(Level::Beginner));
+        assert_eq!("expert".parse(), Ok(Level::Expert));
+        assert!("grandmaster".parse::<Level>().is_err());
+    }
+
+    #[test]
+    fn test_levels_answer_within_time_limit() {
+        let limit = Duration::from_millis(20);
+        // generous, a loaded machine shouldn't fail the test
+        let allowed = limit * 5;
+        for level in [Level::Beginner, Level::Intermediate, Level::Expert] {
+            let mut bot = Bot::with_seed(BotConfig::new(level).with_time_limit(limit), 1);
+            let mut game = Game::new(0);
+            let mut auction = Auction::new(0);
+            while !auction.is_finished() {
+                let player = auction.next_bidder.unwrap();
+                let state = game.get_player_game_state(player);
+                let start = Instant::now();
+                if auction.bidding_phase_started() {
+                    let bid = bot.get_bid(&state, &auction);
+                    auction.bid(bid);
+                } else {
+                    let intent = bot.get_intent(&state, &auction);
+                    auction.announce_intent(intent);
+                }
+                assert!(start.elapsed() < allowed);
+            }
+            game.contract = auction.winning_contract();
+            game.declarer = auction.highest_bidder;
+            while !game.is_over() {
+                let player = game.next_player;
+                let state = game.get_player_game_state(player);
+                let legal = game.get_legal_actions(&game.hands[player]);
+                let start = Instant::now();
+                let card = bot.get_play(&state, legal.clone());
+                assert!(start.elapsed() < allowed, "{:?} took too long", level);
+                assert!(legal.contains(&&card));
+                game.play_card(card);
+            }
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use crate::evaluation::{bidding_position, ContractEstimate, HandEvaluator};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{
+    Auction, AuctionAction, Card, Contract, Game, Hand, PlayerGameState, Value,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Number of abstract bidding actions.
+pub const ACTIONS: usize = 5;
+/// Number of strength levels every contract class is bucketed into.
+const LEVELS: usize = 4;
+const HEADER: &str = "# hothead bidding policy v1";
+
+/// Abstract bidding action. The trainer only decides which kind of game to bid,
+/// the concrete contract of a kind is the one the hand evaluator likes most.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BidAction {
+    Pass,
+    Intent,
+    Call,
+    Wenz,
+    Solo,
+}
+
+impl BidAction {
+    pub const ALL: [BidAction; ACTIONS] = [
+        BidAction::Pass,
+        BidAction::Intent,
+        BidAction::Call,
+        BidAction::Wenz,
+        BidAction::Solo,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            BidAction::Pass => 'p',
+            BidAction::Intent => 'i',
+            BidAction::Call => 'c',
+            BidAction::Wenz => 'w',
+            BidAction::Solo => 's',
+        }
+    }
+
+    fn of(action: &AuctionAction) -> BidAction {
+        match action {
+            AuctionAction::Intent(true) => BidAction::Intent,
+            AuctionAction::Intent(false) | AuctionAction::Bid(Contract::None) => BidAction::Pass,
+            AuctionAction::Bid(Contract::Call(_)) => BidAction::Call,
+            AuctionAction::Bid(Contract::Wenz) => BidAction::Wenz,
+            AuctionAction::Bid(_) => BidAction::Solo,
+        }
+    }
+
+    /// Kind of game the action bids, as an index into [`class_estimates`].
+    fn class(self) -> Option<usize> {
+        match self {
+            BidAction::Call => Some(0),
+            BidAction::Wenz => Some(1),
+            BidAction::Solo => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// The most profitable call, wenz and solo the hand can declare, in that order.
+pub fn class_estimates(
+    evaluator: &HandEvaluator,
+    hand: &Hand,
+    position: usize,
+) -> [Option<ContractEstimate>; 3] {
+    let mut best: [Option<ContractEstimate>; 3] = [None; 3];
+    // sorted by expected value, so the first of every kind is the best one
+    for estimate in evaluator.evaluate_all(hand, position) {
+        let class = match estimate.contract {
+            Contract::Call(_) => 0,
+            Contract::Wenz => 1,
+            _ => 2,
+        };
+        if best[class].is_none() {
+            best[class] = Some(estimate);
+        }
+    }
+    best
+}
+
+/// Abstraction of a hand: the win probability of its best call, wenz and solo,
+/// each cut into a few levels.
+fn bucket(estimates: &[Option<ContractEstimate>; 3]) -> usize {
+    estimates.iter().fold(0, |bucket, estimate| {
+        let level = estimate
+            .map(|e| ((e.win_probability * LEVELS as f64) as usize).min(LEVELS - 1))
+            .unwrap_or(0);
+        bucket * LEVELS + level
+    })
+}
+
+/// Key of the information set: seat, hand bucket and what was announced so far.
+fn info_set_key(position: usize, bucket: usize, history: &[(usize, AuctionAction)]) -> String {
+    let history = history
+        .iter()
+        .map(|(_, action)| BidAction::of(action).symbol())
+        .collect::<String>();
+    format!("{}:{}:{}", position, bucket, history)
+}
+
+/// Abstract actions available to the next bidder.
+fn legal_actions(auction: &Auction, hand: &Hand) -> Vec<BidAction> {
+    if !auction.bidding_phase_started() {
+        return vec![BidAction::Pass, BidAction::Intent];
+    }
+    let bids = auction.valid_bids(Some(hand));
+    BidAction::ALL
+        .into_iter()
+        .filter(|a| *a != BidAction::Intent)
+        .filter(|a| {
+            bids.iter()
+                .any(|b| BidAction::of(&AuctionAction::Bid(*b)) == *a)
+        })
+        .collect()
+}
+
+/// Strategy over the legal actions, proportional to the given weights.
+/// Falls back to a uniform strategy if none of them has a positive weight.
+fn normalize(weights: &[f64; ACTIONS], legal: &[BidAction]) -> [f64; ACTIONS] {
+    let mut strategy = [0.0; ACTIONS];
+    let total: f64 = legal.iter().map(|a| weights[a.index()].max(0.0)).sum();
+    for action in legal {
+        strategy[action.index()] = if total > 0.0 {
+            weights[action.index()].max(0.0) / total
+        } else {
+            1.0 / legal.len() as f64
+        };
+    }
+    strategy
+}
+
+fn sample_action<R: Rng>(strategy: &[f64; ACTIONS], legal: &[BidAction], rng: &mut R) -> BidAction {
+    *legal
+        .choose_weighted(rng, |a| strategy[a.index()])
+        .unwrap_or(&legal[0])
+}
+
+/// Average strategy of a trained bidding game, one probability per [`BidAction`]
+/// for every information set that was visited during training.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BiddingPolicy {
+    table: HashMap<String, [f64; ACTIONS]>,
+}
+
+impl BiddingPolicy {
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Action probabilities of an information set, indexed by [`BidAction::index`].
+    pub fn probabilities(&self, key: &str) -> Option<&[f64; ACTIONS]> {
+        self.table.get(key)
+    }
+
+    /// Probabilities for the player to move, restricted to the actions they may take.
+    /// `None` if the situation never came up in training.
+    pub fn strategy(
+        &self,
+        evaluator: &HandEvaluator,
+        state: &PlayerGameState,
+        auction: &Auction,
+    ) -> Option<(Vec<BidAction>, [f64; ACTIONS])> {
+        let position = bidding_position(state);
+        let bucket = bucket(&class_estimates(evaluator, state.hand, position));
+        let key = info_set_key(position, bucket, &auction.history);
+        let probabilities = self.table.get(&key)?;
+        let legal = legal_actions(auction, state.hand);
+        if legal.iter().all(|a| probabilities[a.index()] <= 0.0) {
+            return None;
+        }
+        let strategy = normalize(probabilities, &legal);
+        Some((legal, strategy))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<BiddingPolicy> {
+        BiddingPolicy::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the table as text, one information set per line followed by its probabilities.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        let mut keys = self.table.keys().collect::<Vec<&String>>();
+        keys.sort();
+        for key in keys {
+            let probabilities = self.table[key]
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(" ");
+            writeln!(writer, "{} {}", key, probabilities)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<BiddingPolicy> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid policy line: {}", line),
+            )
+        };
+        let mut table = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let key = parts.next().ok_or_else(|| invalid(&line))?;
+            let mut probabilities = [0.0; ACTIONS];
+            for probability in probabilities.iter_mut() {
+                *probability = parts
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .ok_or_else(|| invalid(&line))?;
+            }
+            if parts.next().is_some() {
+                return Err(invalid(&line));
+            }
+            table.insert(key.to_string(), probabilities);
+        }
+        Ok(BiddingPolicy { table })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct InfoSet {
+    regret: [f64; ACTIONS],
+    strategy_sum: [f64; ACTIONS],
+}
+
+/// A dealt hand as the trainer sees it.
+struct Deal {
+    hands: [Hand; 4],
+    buckets: [usize; 4],
+    estimates: [[Option<ContractEstimate>; 3]; 4],
+}
+
+/// External sampling Monte Carlo CFR over the auction.
+///
+/// Every iteration deals new cards and walks the auction once per player: all actions of
+/// that player are explored, everybody else samples from their current strategy. The games
+/// themselves aren't played, a finished auction is paid out with the expected value the
+/// hand evaluator gives the winning contract, so the trainer learns how to bid against other
+/// bidders rather than how strong a hand is.
+pub struct CfrTrainer {
+    pub evaluator: HandEvaluator,
+    pub iterations: usize,
+    info_sets: HashMap<String, InfoSet>,
+    rng: StdRng,
+}
+
+impl CfrTrainer {
+    pub fn new(evaluator: HandEvaluator) -> CfrTrainer {
+        CfrTrainer::with_rng(evaluator, StdRng::from_entropy())
+    }
+
+    pub fn with_seed(evaluator: HandEvaluator, seed: u64) -> CfrTrainer {
+        CfrTrainer::with_rng(evaluator, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(evaluator: HandEvaluator, rng: StdRng) -> CfrTrainer {
+        CfrTrainer {
+            evaluator,
+            iterations: 0,
+            info_sets: HashMap::new(),
+            rng,
+        }
+    }
+
+    pub fn train(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            let deal = self.deal();
+            for traverser in 0..4 {
+                self.traverse(&deal, Auction::new(0), traverser);
+            }
+            self.iterations += 1;
+        }
+    }
+
+    /// The average strategy so far.
+    pub fn policy(&self) -> BiddingPolicy {
+        let table = self
+            .info_sets
+            .iter()
+            .filter(|(_, info_set)| info_set.strategy_sum.iter().sum::<f64>() > 0.0)
+            .map(|(key, info_set)| {
+                let total: f64 = info_set.strategy_sum.iter().sum();
+                (key.clone(), info_set.strategy_sum.map(|s| s / total))
+            })
+            .collect();
+        BiddingPolicy { table }
+    }
+
+    fn deal(&mut self) -> Deal {
+        // seats are relative to forehand, so forehand is always player 0
+        let mut cards = Card::all();
+        cards.shuffle(&mut self.rng);
+        let game = Game::from_hands(
+            0,
+            [
+                cards[0..8].to_vec(),
+                cards[8..16].to_vec(),
+                cards[16..24].to_vec(),
+                cards[24..32].to_vec(),
+            ],
+        );
+        let estimates = [0, 1, 2, 3].map(|p| class_estimates(&self.evaluator, &game.hands[p], p));
+        Deal {
+            buckets: estimates.map(|e| bucket(&e)),
+            hands: game.hands,
+            estimates,
+        }
+    }
+
+    fn traverse(&mut self, deal: &Deal, auction: Auction, traverser: usize) -> f64 {
+        let player = match auction.next_bidder {
+            Some(player) if !auction.is_finished() => player,
+            _ => return payoff(deal, &auction)[traverser],
+        };
+        let legal = legal_actions(&auction, &deal.hands[player]);
+        let key = info_set_key(player, deal.buckets[player], &auction.history);
+        let regret = self.info_sets.entry(key.clone()).or_default().regret;
+        let strategy = normalize(&regret, &legal);
+
+        if player == traverser {
+            let mut values = [0.0; ACTIONS];
+            let mut value = 0.0;
+            for action in legal.iter() {
+                let next = apply(deal, &auction, player, *action);
+                values[action.index()] = self.traverse(deal, next, traverser);
+                value += strategy[action.index()] * values[action.index()];
+            }
+            let info_set = self.info_sets.get_mut(&key).expect("Created above.");
+            for action in legal.iter() {
+                info_set.regret[action.index()] += values[action.index()] - value;
+            }
+            value
+        } else {
+            let info_set = self.info_sets.get_mut(&key).expect("Created above.");
+            for action in legal.iter() {
+                info_set.strategy_sum[action.index()] += strategy[action.index()];
+            }
+            let action = sample_action(&strategy, &legal, &mut self.rng);
+            let next = apply(deal, &auction, player, action);
+            self.traverse(deal, next, traverser)
+        }
+    }
+}
+
+fn apply(deal: &Deal, auction: &Auction, player: usize, action: BidAction) -> Auction {
+    let mut next = auction.clone();
+    match action {
+        BidAction::Intent => next.announce_intent(true),
+        BidAction::Pass if !next.bidding_phase_started() => next.announce_intent(false),
+        BidAction::Pass => next.bid(Contract::None),
+        _ => {
+            let class = action.class().expect("Only bids are left.");
+            let estimate = deal.estimates[player][class].expect("Only legal classes are bid.");
+            next.bid(estimate.contract);
+        }
+    }
+    next
+}
+
+/// Expected earnings of every player once the auction is over.
+fn payoff(deal: &Deal, auction: &Auction) -> [f64; 4] {
+    let contract = auction.winning_contract();
+    let declarer = auction.highest_bidder;
+    let estimate = deal.estimates[declarer]
+        .iter()
+        .flatten()
+        .find(|e| e.contract == contract);
+    let value = match estimate {
+        Some(estimate) => estimate.expected_value,
+        // a ramsch is a lottery between the four players
+        None => return [0.0; 4],
+    };
+    let mut payoff = [0.0; 4];
+    match contract {
+        Contract::Call(suit) => {
+            let ace = Card {
+                suit,
+                value: Value::Ace,
+            };
+            for (player, hand) in deal.hands.iter().enumerate() {
+                payoff[player] = if player == declarer || hand.cards.contains(&ace) {
+                    value
+                } else {
+                    -value
+                };
+            }
+        }
+        _ => {
+            for (player, p) in payoff.iter_mut().enumerate() {
+                *p = if player == declarer {
+                    value
+                } else {
+                    -value / 3.0
+                };
+            }
+        }
+    }
+    payoff
+}
+
+/// Bids with a trained policy and leaves everything else to another agent.
+/// Situations the policy doesn't know are handed to the other agent as well.
+pub struct PolicyBidder<A: Agent> {
+    pub policy: BiddingPolicy,
+    pub inner: A,
+    evaluator: HandEvaluator,
+    rng: StdRng,
+}
+
+impl<A: Agent> PolicyBidder<A> {
+    pub fn new(policy: BiddingPolicy, inner: A) -> PolicyBidder<A> {
+        PolicyBidder {
+            policy,
+            inner,
+            evaluator: HandEvaluator::default(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_seed(policy: BiddingPolicy, inner: A, seed: u64) -> PolicyBidder<A> {
+        PolicyBidder {
+            rng: StdRng::seed_from_u64(seed),
+            ..PolicyBidder::new(policy, inner)
+        }
+    }
+
+    fn choose(&mut self, state: &PlayerGameState, auction: &Auction) -> Option<BidAction> {
+        let (legal, strategy) = self.policy.strategy(&self.evaluator, state, auction)?;
+        Some(sample_action(&strategy, &legal, &mut self.rng))
+    }
+}
+
+impl<A: Agent> Agent for PolicyBidder<A> {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        match self.choose(state, auction) {
+            Some(action) => action == BidAction::Intent,
+            None => self.inner.get_intent(state, auction),
+        }
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        let estimates = class_estimates(&self.evaluator, state.hand, bidding_position(state));
+        match self.choose(state, auction) {
+            Some(BidAction::Pass) => Contract::None,
+            Some(action) => match action.class().and_then(|class| estimates[class]) {
+                Some(estimate) => estimate.contract,
+                None => self.inner.get_bid(state, auction),
+            },
+            None => self.inner.get_bid(state, auction),
+        }
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        self.inner.get_play(state, legal_plays)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::HeuristicAgent;
+    use crate::testing::seeded_game;
+    use schafkopf_lib::schafkopf_env::game_logic::Suit;
+    use std::sync::OnceLock;
+
+    /// Training takes most of the time, so the tests share one policy.
+    fn trained() -> &'static BiddingPolicy {
+        static POLICY: OnceLock<BiddingPolicy> = OnceLock::new();
+        POLICY.get_or_init(|| {
+            let mut trainer = CfrTrainer::with_seed(HandEvaluator::default(), 1);
+            trainer.train(3000);
+            trainer.policy()
+        })
+    }
+
+    #[test]
+    fn test_policy_round_trip() {
+        let policy = trained();
+        assert!(!policy.is_empty());
+        let mut buffer = Vec::new();
+        policy.write_to(&mut buffer).unwrap();
+        let loaded = BiddingPolicy::read_from(&buffer[..]).unwrap();
+        assert_eq!(loaded.len(), policy.len());
+        for (key, probabilities) in policy.table.iter() {
+            let total: f64 = probabilities.iter().sum();
+            assert!((total - 1.0).abs() < 1e-9);
+            assert_eq!(loaded.probabilities(key), Some(probabilities));
+        }
+        assert!(BiddingPolicy::read_from("0:0: 1 2".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_weak_hands_pass_and_strong_hands_play() {
+        let policy = trained();
+        let weak = [
+            (Suit::Bells, Value::Seven),
+            (Suit::Bells, Value::Eight),
+            (Suit::Bells, Value::King),
+            (Suit::Leaves, Value::Seven),
+            (Suit::Leaves, Value::Nine),
+            (Suit::Acorns, Value::Eight),
+            (Suit::Acorns, Value::Nine),
+            (Suit::Hearts, Value::Seven),
+        ];
+        let strong = [
+            (Suit::Acorns, Value::Ober),
+            (Suit::Leaves, Value::Ober),
+            (Suit::Hearts, Value::Ober),
+            (Suit::Bells, Value::Ober),
+            (Suit::Acorns, Value::Under),
+            (Suit::Leaves, Value::Under),
+            (Suit::Hearts, Value::Ace),
+            (Suit::Hearts, Value::Ten),
+        ];
+        let evaluator = HandEvaluator::default();
+        for (cards, expected) in [(weak, BidAction::Pass), (strong, BidAction::Intent)] {
+            let cards = cards.map(|(suit, value)| Card { suit, value });
+            let rest = Card::all()
+                .into_iter()
+                .filter(|c| !cards.contains(c))
+                .collect::<Vec<Card>>();
+            let game = Game::from_hands(
+                0,
+                [
+                    cards.to_vec(),
+                    rest[0..8].to_vec(),
+                    rest[8..16].to_vec(),
+                    rest[16..24].to_vec(),
+                ],
+            );
+            let state = game.get_player_game_state(0);
+            let (_, strategy) = policy
+                .strategy(&evaluator, &state, &Auction::new(0))
+                .unwrap();
+            assert!(strategy[expected.index()] > 0.5);
+        }
+    }
+
+    #[test]
+    fn test_policy_bidder_finishes_auctions() {
+        let policy = trained();
+        let mut bidder = PolicyBidder::with_seed(policy.clone(), HeuristicAgent::new(), 3);
+        for seed in 0..50 {
+            let game = seeded_game(0, seed);
+            let mut auction = Auction::new(0);
+            while let Some(player) = auction.next_bidder {
+                if auction.is_finished() {
+                    break;
+                }
+                let state = game.get_player_game_state(player);
+                if auction.bidding_phase_started() {
+                    let bid = bidder.get_bid(&state, &auction);
+                    assert!(auction.valid_bids(Some(&game.hands[player])).contains(&bid));
+                    auction.bid(bid);
+                } else {
+                    let intent = bidder.get_intent(&state, &auction);
+                    auction.announce_intent(intent);
+                }
+            }
+            assert_ne!(auction.winning_contract(), Contract::None);
+        }
+    }
+}
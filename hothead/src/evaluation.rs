@@ -0,0 +1,293 @@
+use schafkopf_lib::schafkopf_env::game_logic::{
+    get_trump_order, is_trump, trump_rank, Auction, Card, Contract, Hand, PlayerGameState, RuleSet,
+    Suit, Value,
+};
+use strum::IntoEnumIterator;
+
+/// Estimated outcome of playing a contract with a given hand.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ContractEstimate {
+    pub contract: Contract,
+    /// Probability that the declaring party wins the game.
+    pub win_probability: f64,
+    /// Expected earnings of the declarer, in the units of the rule set's tariffs.
+    pub expected_value: f64,
+}
+
+/// Scores hands for bidding decisions.
+///
+/// The evaluator is purely heuristic: every contract gets a strength score built from
+/// the trumps, top trumps, free aces, suit lengths and the bidding position, which is
+/// then mapped to a win probability with a logistic curve.
+#[derive(Clone, Debug)]
+pub struct HandEvaluator {
+    pub rules: RuleSet,
+}
+
+impl Default for HandEvaluator {
+    fn default() -> HandEvaluator {
+        HandEvaluator::new(RuleSet::default())
+    }
+}
+
+impl HandEvaluator {
+    pub fn new(rules: RuleSet) -> HandEvaluator {
+        HandEvaluator { rules }
+    }
+
+    /// Estimates the outcome of declaring `contract` with `hand`.
+    /// `position` is the seat relative to forehand, 0 being forehand and 3 the dealer.
+    pub fn evaluate(&self, hand: &Hand, contract: Contract, position: usize) -> ContractEstimate {
+        let (score, threshold) = match contract {
+            Contract::Call(suit) => (call_score(hand, suit, position), 6.0),
+            Contract::Solo(_) => (solo_score(hand, &contract, position), 10.2),
+            Contract::Wenz => (wenz_score(hand, position), 6.2),
+            Contract::Ramsch | Contract::None => {
+                return ContractEstimate {
+                    contract,
+                    win_probability: 0.0,
+                    expected_value: 0.0,
+                }
+            }
+        };
+        let win_probability = 1.0 / (1.0 + (-(score - threshold) * 1.4).exp());
+
+        let laufende = count_laufende(hand, &contract);
+        let mut payout = self.rules.tariff(&contract) as f64;
+        if laufende >= self.rules.min_laufende(&contract) {
+            payout += (laufende * self.rules.laufende_bonus as usize) as f64;
+        }
+        if !matches!(contract, Contract::Call(_)) {
+            // a soloist settles with all three opponents
+            payout *= 3.0;
+        }
+
+        ContractEstimate {
+            contract,
+            win_probability,
+            expected_value: win_probability * payout - (1.0 - win_probability) * payout,
+        }
+    }
+
+    /// Estimates every contract the hand is allowed to declare, best expected value first.
+    pub fn evaluate_all(&self, hand: &Hand, position: usize) -> Vec<ContractEstimate> {
+        let mut estimates = Auction::new(0)
+            .valid_bids(Some(hand))
+            .into_iter()
+            .map(|c| self.evaluate(hand, c, position))
+            .collect::<Vec<ContractEstimate>>();
+        estimates.sort_by(|a, b| b.expected_value.total_cmp(&a.expected_value));
+        estimates
+    }
+
+    /// Returns the most profitable contract, if any of them is expected to make money.
+    pub fn best_contract(&self, hand: &Hand, position: usize) -> Option<ContractEstimate> {
+        self.evaluate_all(hand, position)
+            .into_iter()
+            .next()
+            .filter(|e| e.expected_value > 0.0)
+    }
+
+    /// Announces the intent to play if any contract is expected to make money.
+    pub fn intent(&self, state: &PlayerGameState) -> bool {
+        self.best_contract(state.hand, bidding_position(state))
+            .is_some()
+    }
+
+    /// Bids the most profitable contract still allowed, or passes if none is expected to make money.
+    /// If passing isn't allowed, the least bad contract is bid.
+    pub fn bid(&self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        let valid_bids = auction.valid_bids(Some(state.hand));
+        let best = valid_bids
+            .iter()
+            .filter(|c| **c != Contract::None)
+            .map(|c| self.evaluate(state.hand, *c, bidding_position(state)))
+            .max_by(|a, b| a.expected_value.total_cmp(&b.expected_value));
+        match best {
+            Some(estimate) if estimate.expected_value > 0.0 => estimate.contract,
+            _ if valid_bids.contains(&Contract::None) => Contract::None,
+            Some(estimate) => estimate.contract,
+            None => Contract::None,
+        }
+    }
+}
+
+/// Seat of the player relative to forehand, 0 being forehand.
+pub fn bidding_position(state: &PlayerGameState) -> usize {
+    (state.player_nr + 4 - state.forehand) % 4
+}
+
+/// Trump strength for the Ober/Unter based contracts, each trump counting more the higher it ranks.
+fn trump_strength(hand: &Hand, contract: &Contract) -> f64 {
+    hand.cards
+        .iter()
+        .filter_map(|c| trump_rank(c, contract))
+        .map(|rank| 1.0 + 0.06 * rank as f64)
+        .sum()
+}
+
+/// Aces of suits that aren't trump.
+fn free_aces(hand: &Hand, contract: &Contract) -> usize {
+    hand.cards
+        .iter()
+        .filter(|c| c.value == Value::Ace && !is_trump(c, contract))
+        .count()
+}
+
+/// Number of non-trump cards the hand holds in the given suit.
+fn suit_length(hand: &Hand, suit: Suit, contract: &Contract) -> usize {
+    hand.cards
+        .iter()
+        .filter(|c| c.suit == suit && !is_trump(c, contract))
+        .count()
+}
+
+/// Number of suits, other than the trump suit, the hand is void in.
+fn voids(hand: &Hand, contract: &Contract) -> usize {
+    let trump_suit = match contract {
+        Contract::Call(_) | Contract::Ramsch => Some(Suit::Hearts),
+        Contract::Solo(suit) => Some(*suit),
+        _ => None,
+    };
+    Suit::iter()
+        .filter(|s| Some(*s) != trump_suit && suit_length(hand, *s, contract) == 0)
+        .count()
+}
+
+/// Number of consecutive top trumps held, counted from the highest one.
+fn count_laufende(hand: &Hand, contract: &Contract) -> usize {
+    get_trump_order(contract)
+        .iter()
+        .rev()
+        .take_while(|c| hand.cards.contains(c))
+        .count()
+}
+
+fn position_bonus(position: usize, forehand_bonus: f64) -> f64 {
+    if position == 0 {
+        forehand_bonus
+    } else {
+        0.0
+    }
+}
+
+fn call_score(hand: &Hand, called: Suit, position: usize) -> f64 {
+    let contract = Contract::Call(called);
+    let mut score = trump_strength(hand, &contract);
+    score += 0.7 * free_aces(hand, &contract) as f64;
+    score += 0.35 * voids(hand, &contract) as f64;
+    if suit_length(hand, called, &contract) >= 4 {
+        // long in the called suit: the partner's ace is likely trumped
+        score -= 0.5;
+    }
+    score + position_bonus(position, 0.2)
+}
+
+fn solo_score(hand: &Hand, contract: &Contract, position: usize) -> f64 {
+    let mut score = trump_strength(hand, contract);
+    score += 0.8 * free_aces(hand, contract) as f64;
+    score += 0.45 * voids(hand, contract) as f64;
+    score + position_bonus(position, 0.4)
+}
+
+fn wenz_score(hand: &Hand, position: usize) -> f64 {
+    let contract = Contract::Wenz;
+    let mut score = hand
+        .cards
+        .iter()
+        .filter_map(|c| trump_rank(c, &contract))
+        .map(|rank| 1.4 + 0.1 * (rank - 8) as f64)
+        .sum::<f64>();
+    for suit in Suit::iter() {
+        let has = |value: Value| hand.cards.contains(&Card { suit, value });
+        if has(Value::Ace) {
+            score += 1.1;
+            if has(Value::Ten) {
+                score += 0.6;
+            }
+            // every further card of a suit headed by the ace is likely a trick
+            score += 0.3 * suit_length(hand, suit, &contract).saturating_sub(2) as f64;
+        }
+    }
+    score + position_bonus(position, 0.6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(cards: &[(Suit, Value)]) -> Hand {
+        Hand {
+            cards: cards
+                .iter()
+                .map(|(suit, value)| Card {
+                    suit: *suit,
+                    value: *value,
+                })
+                .collect(),
+            played: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_strong_solo() {
+        let hand = hand(&[
+            (Suit::Acorns, Value::Ober),
+            (Suit::Leaves, Value::Ober),
+            (Suit::Hearts, Value::Ober),
+            (Suit::Acorns, Value::Under),
+            (Suit::Hearts, Value::Ace),
+            (Suit::Hearts, Value::Ten),
+            (Suit::Hearts, Value::King),
+            (Suit::Acorns, Value::Ace),
+        ]);
+        let evaluator = HandEvaluator::default();
+        let estimate = evaluator.evaluate(&hand, Contract::Solo(Suit::Hearts), 0);
+        assert!(estimate.win_probability > 0.9);
+        assert_eq!(
+            evaluator.best_contract(&hand, 0).unwrap().contract,
+            Contract::Solo(Suit::Hearts)
+        );
+    }
+
+    #[test]
+    fn test_weak_hand_passes() {
+        let hand = hand(&[
+            (Suit::Acorns, Value::Seven),
+            (Suit::Acorns, Value::Eight),
+            (Suit::Leaves, Value::Seven),
+            (Suit::Leaves, Value::Nine),
+            (Suit::Bells, Value::Seven),
+            (Suit::Bells, Value::Eight),
+            (Suit::Bells, Value::King),
+            (Suit::Hearts, Value::Seven),
+        ]);
+        let evaluator = HandEvaluator::default();
+        assert!(evaluator.best_contract(&hand, 2).is_none());
+        assert!(evaluator
+            .evaluate_all(&hand, 2)
+            .iter()
+            .all(|e| e.win_probability < 0.5));
+    }
+
+    #[test]
+    fn test_no_call_on_held_ace() {
+        let hand = hand(&[
+            (Suit::Acorns, Value::Ace),
+            (Suit::Acorns, Value::Eight),
+            (Suit::Leaves, Value::Seven),
+            (Suit::Leaves, Value::Ober),
+            (Suit::Bells, Value::Under),
+            (Suit::Bells, Value::Eight),
+            (Suit::Hearts, Value::King),
+            (Suit::Hearts, Value::Seven),
+        ]);
+        let estimates = HandEvaluator::default().evaluate_all(&hand, 1);
+        assert!(!estimates
+            .iter()
+            .any(|e| e.contract == Contract::Call(Suit::Acorns)));
+        assert!(estimates
+            .iter()
+            .any(|e| e.contract == Contract::Call(Suit::Leaves)));
+    }
+}
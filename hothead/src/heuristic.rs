@@ -0,0 +1,192 @@
+use crate::evaluation::HandEvaluator;
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{
+    beats, is_trump, plain_rank, trump_rank, Auction, Card, Contract, PlayedCard, PlayerGameState,
+    Value,
+};
+
+/// Rule based agent: bids with the hand evaluator and plays by a handful of
+/// well known rules of thumb. Cheap enough to be used as a rollout policy.
+#[derive(Default)]
+pub struct HeuristicAgent {
+    evaluator: HandEvaluator,
+}
+
+impl HeuristicAgent {
+    pub fn new() -> HeuristicAgent {
+        HeuristicAgent::default()
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn get_intent(&mut self, state: &PlayerGameState, _auction: &Auction) -> bool {
+        self.evaluator.intent(state)
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        self.evaluator.bid(state, auction)
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        let contract = state.contract;
+        let trick = current_trick(state);
+        if contract == Contract::Ramsch {
+            return ramsch_play(trick, &legal_plays, &contract);
+        }
+
+        if trick.is_empty() {
+            let declaring = is_partner(state, state.declarer) == Some(true);
+            let trumps = legal_plays.iter().filter(|c| is_trump(c, &contract));
+            if declaring {
+                // the declaring side pulls trumps
+                if let Some(card) = trumps.max_by_key(|c| strength(c, &contract)) {
+                    return **card;
+                }
+            }
+            if let Some(card) = legal_plays
+                .iter()
+                .find(|c| c.value == Value::Ace && !is_trump(c, &contract))
+            {
+                return **card;
+            }
+            return lowest(&legal_plays, &contract);
+        }
+
+        let winner = trick_winner(trick, &contract);
+        if is_partner(state, winner.1) == Some(true) {
+            // partner takes the trick: schmier
+            return **legal_plays
+                .iter()
+                .max_by_key(|c| (c.points(), !is_trump(c, &contract)))
+                .unwrap();
+        }
+        let winning = legal_plays
+            .iter()
+            .filter(|c| beats(c, &winner.0, &contract))
+            .min_by_key(|c| strength(c, &contract));
+        match winning {
+            Some(card) => **card,
+            None => lowest(&legal_plays, &contract),
+        }
+    }
+}
+
+/// In a Ramsch every point taken counts against the player: lead low, stay under the
+/// card that takes the trick, and if the trick can't be avoided get rid of the highest card.
+fn ramsch_play(trick: &[PlayedCard], legal_plays: &[&Card], contract: &Contract) -> Card {
+    if trick.is_empty() {
+        return **legal_plays
+            .iter()
+            .min_by_key(|c| (strength(c, contract), c.points()))
+            .unwrap();
+    }
+    let winner = trick_winner(trick, contract);
+    let ducking = legal_plays
+        .iter()
+        .filter(|c| !beats(c, &winner.0, contract))
+        .max_by_key(|c| (c.points(), strength(c, contract)));
+    match ducking {
+        Some(card) => **card,
+        None => **legal_plays
+            .iter()
+            .max_by_key(|c| strength(c, contract))
+            .unwrap(),
+    }
+}
+
+/// The card that takes the trick so far.
+fn trick_winner(trick: &[PlayedCard], contract: &Contract) -> PlayedCard {
+    let mut winner = trick[0];
+    for played in trick.iter().skip(1) {
+        if beats(&played.0, &winner.0, contract) {
+            winner = *played;
+        }
+    }
+    winner
+}
+
+/// Whether `other` is on the player's side, as far as the player can tell.
+pub fn is_partner(state: &PlayerGameState, other: usize) -> Option<bool> {
+    let me = state.player_nr;
+    if other == me {
+        return Some(true);
+    }
+    match state.contract {
+        Contract::Solo(_) | Contract::Wenz => {
+            Some((me == state.declarer) == (other == state.declarer))
+        }
+        Contract::Call(suit) => {
+            let ace = Card {
+                suit,
+                value: Value::Ace,
+            };
+            if state.hand.cards.contains(&ace) {
+                return Some(other == state.declarer);
+            }
+            let ace_owner = state.played.iter().find(|p| p.0 == ace).map(|p| p.1);
+            match ace_owner {
+                Some(owner) if me == state.declarer => Some(other == owner),
+                Some(owner) => Some(other != owner && other != state.declarer),
+                None if me != state.declarer && other == state.declarer => Some(false),
+                None => None,
+            }
+        }
+        _ => Some(false),
+    }
+}
+
+/// The cards of the trick that is currently being played.
+pub fn current_trick<'a>(state: &PlayerGameState<'a>) -> &'a [PlayedCard] {
+    let start = state.played.len() / 4 * 4;
+    &state.played[start..]
+}
+
+/// Strength of a card for comparisons, trumps above all plain cards.
+pub fn strength(card: &Card, contract: &Contract) -> u8 {
+    match trump_rank(card, contract) {
+        Some(rank) => 16 + rank,
+        None => plain_rank(card),
+    }
+}
+
+fn lowest(legal_plays: &[&Card], contract: &Contract) -> Card {
+    **legal_plays
+        .iter()
+        .min_by_key(|c| (c.points(), strength(c, contract)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::seeded_game;
+
+    #[test]
+    fn test_ramsch_avoids_tricks() {
+        for seed in 0..10 {
+            let mut game = seeded_game(0, seed);
+            game.contract = Contract::Ramsch;
+            let mut agent = HeuristicAgent::new();
+            while !game.is_over() {
+                let player = game.next_player;
+                let legal = game
+                    .get_legal_actions(&game.hands[player])
+                    .into_iter()
+                    .copied()
+                    .collect::<Vec<Card>>();
+                let state = game.get_player_game_state(player);
+                let trick = current_trick(&state).to_vec();
+                let card = agent.get_play(&state, legal.iter().collect());
+                if trick.is_empty() {
+                    let lowest = legal.iter().map(|c| strength(c, &game.contract)).min();
+                    assert_eq!(Some(strength(&card, &game.contract)), lowest);
+                } else {
+                    let winner = trick_winner(&trick, &game.contract).0;
+                    let can_duck = legal.iter().any(|c| !beats(c, &winner, &game.contract));
+                    assert_eq!(beats(&card, &winner, &game.contract), !can_duck);
+                }
+                assert!(game.play_card(card));
+            }
+        }
+    }
+}
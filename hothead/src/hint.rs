@@ -0,0 +1,334 @@
+use crate::evaluation::{bidding_position, HandEvaluator};
+use crate::heuristic::{current_trick, is_partner, strength};
+use crate::ismcts::Action;
+use crate::pimc::PimcAgent;
+use schafkopf_lib::schafkopf_env::game_logic::{
+    beats, is_trump, Auction, Card, Contract, Game, PlayerGameState, Value,
+};
+
+/// A candidate decision for a human player, with what it is expected to bring and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub action: Action,
+    /// Card points of the player's side for cards, expected earnings in the rule set's
+    /// tariffs for intents and bids. In a Ramsch everybody plays alone, so for cards it's
+    /// the points of the other three players, higher still being better.
+    pub expected_value: f64,
+    pub reason: String,
+}
+
+/// Suggests and explains moves, e.g. for a hint button or a teaching mode.
+///
+/// Cards are ranked by the PIMC search, bids by the hand evaluator. The reasons are
+/// short rules of thumb describing the candidate, they don't explain the search.
+pub struct HintEngine {
+    pimc: PimcAgent,
+    evaluator: HandEvaluator,
+}
+
+impl HintEngine {
+    /// `samples` is the number of deals the search looks at per card decision.
+    pub fn new(samples: usize) -> HintEngine {
+        HintEngine::with_agent(PimcAgent::new(samples))
+    }
+
+    pub fn with_seed(samples: usize, seed: u64) -> HintEngine {
+        HintEngine::with_agent(PimcAgent::with_seed(samples, seed))
+    }
+
+    fn with_agent(pimc: PimcAgent) -> HintEngine {
+        HintEngine {
+            pimc,
+            evaluator: HandEvaluator::default(),
+        }
+    }
+
+    /// Whether to announce the intent to play, best first.
+    pub fn intent_hints(&self, state: &PlayerGameState, auction: &Auction) -> Vec<Hint> {
+        let competition =
+            self.pimc
+                .opponents
+                .competition(state.player_nr, auction, &self.evaluator);
+        let best = self
+            .evaluator
+            .evaluate_all(state.hand, bidding_position(state))
+            .into_iter()
+            .next();
+        let mut hints = vec![Hint {
+            action: Action::Intent(false),
+            expected_value: 0.0,
+            reason: pass_reason(best.map(|e| e.expected_value - competition)),
+        }];
+        if let Some(estimate) = best {
+            hints.push(Hint {
+                action: Action::Intent(true),
+                expected_value: estimate.expected_value - competition,
+                reason: format!(
+                    "{:.0}% chance to win a {}",
+                    estimate.win_probability * 100.0,
+                    estimate.contract
+                ),
+            });
+        }
+        sorted(hints)
+    }
+
+    /// Every bid still allowed, best first.
+    pub fn bid_hints(&self, state: &PlayerGameState, auction: &Auction) -> Vec<Hint> {
+        let competition =
+            self.pimc
+                .opponents
+                .competition(state.player_nr, auction, &self.evaluator);
+        let valid_bids = auction.valid_bids(Some(state.hand));
+        let estimates = valid_bids
+            .iter()
+            .filter(|c| **c != Contract::None)
+            .map(|c| {
+                self.evaluator
+                    .evaluate(state.hand, *c, bidding_position(state))
+            })
+            .collect::<Vec<_>>();
+        let best = estimates
+            .iter()
+            .map(|e| e.expected_value - competition)
+            .max_by(|a, b| a.total_cmp(b));
+
+        let mut hints = estimates
+            .iter()
+            .map(|estimate| Hint {
+                action: Action::Bid(estimate.contract),
+                expected_value: estimate.expected_value - competition,
+                reason: format!(
+                    "{:.0}% chance to win the {}",
+                    estimate.win_probability * 100.0,
+                    estimate.contract
+                ),
+            })
+            .collect::<Vec<Hint>>();
+        if valid_bids.contains(&Contract::None) {
+            hints.push(Hint {
+                action: Action::Bid(Contract::None),
+                expected_value: 0.0,
+                reason: pass_reason(best),
+            });
+        }
+        sorted(hints)
+    }
+
+    /// Every legal card, best first, valued by the card points the player's side
+    /// is expected to end up with, or in a Ramsch the other three players.
+    pub fn card_hints(&mut self, state: &PlayerGameState, auction: Option<&Auction>) -> Vec<Hint> {
+        self.pimc.set_auction(auction.cloned());
+        let values = self.pimc.card_values(state);
+        let legal = values.iter().map(|(card, _)| *card).collect::<Vec<Card>>();
+        let hints = values
+            .into_iter()
+            .map(|(card, value)| Hint {
+                action: Action::Play(card),
+                expected_value: value,
+                reason: card_reason(state, &legal, card),
+            })
+            .collect();
+        sorted(hints)
+    }
+
+    /// Lets the search learn the other players' habits from a finished game.
+    pub fn game_over(&mut self, auction: &Auction, game: &Game) {
+        self.pimc.opponents.observe(auction, game);
+    }
+}
+
+fn sorted(mut hints: Vec<Hint>) -> Vec<Hint> {
+    hints.sort_by(|a, b| b.expected_value.total_cmp(&a.expected_value));
+    hints
+}
+
+fn pass_reason(best: Option<f64>) -> String {
+    match best {
+        Some(value) if value > 0.0 => "safe, but the hand is strong enough to play".to_string(),
+        _ => "too weak, pass".to_string(),
+    }
+}
+
+/// A rule of thumb describing what playing `card` does in the current trick.
+fn card_reason(state: &PlayerGameState, legal: &[Card], card: Card) -> String {
+    if legal.len() == 1 {
+        return "only legal card".to_string();
+    }
+    let contract = state.contract;
+    let called_ace = match contract {
+        Contract::Call(suit) => Some(Card {
+            suit,
+            value: Value::Ace,
+        }),
+        _ => None,
+    };
+    let in_called_suit =
+        |c: &Card| called_ace.is_some_and(|ace| c.suit == ace.suit && !is_trump(c, &contract));
+    let trick = current_trick(state);
+
+    if trick.is_empty() {
+        let declaring = is_partner(state, state.declarer) == Some(true);
+        let ace_played = called_ace.is_some_and(|ace| state.played.iter().any(|p| p.0 == ace));
+        return if Some(card) == called_ace {
+            format!("lead the called {}", card)
+        } else if in_called_suit(&card) && state.player_nr == state.declarer && !ace_played {
+            "search the called ace".to_string()
+        } else if is_trump(&card, &contract) && declaring && contract != Contract::Ramsch {
+            "draw trumps".to_string()
+        } else if is_trump(&card, &contract) {
+            "lead a trump".to_string()
+        } else if card.value == Value::Ace {
+            format!("cash the {}", card)
+        } else if card.points() == 0 {
+            "lead a low card".to_string()
+        } else {
+            format!("lead the {}", card)
+        };
+    }
+
+    let mut winner = trick[0];
+    for played in trick.iter().skip(1) {
+        if beats(&played.0, &winner.0, &contract) {
+            winner = *played;
+        }
+    }
+    let takes = beats(&card, &winner.0, &contract);
+    let last = trick.len() == 3;
+    if is_partner(state, winner.1) == Some(true) && (!takes || last) {
+        return if card.points() >= 10 {
+            format!("partner is winning, schmier the {}", card)
+        } else {
+            "partner is winning".to_string()
+        };
+    }
+    if takes {
+        let cheapest = legal
+            .iter()
+            .filter(|c| beats(c, &winner.0, &contract))
+            .all(|c| strength(c, &contract) >= strength(&card, &contract));
+        return match (last, cheapest) {
+            (true, _) => "takes the trick".to_string(),
+            (false, true) => "goes for the trick as cheaply as possible".to_string(),
+            (false, false) => "goes for the trick with a high card".to_string(),
+        };
+    }
+    let following = trick[0].0.suit == card.suit
+        && is_trump(&trick[0].0, &contract) == is_trump(&card, &contract);
+    if !following
+        && state.player_nr == state.declarer
+        && !in_called_suit(&card)
+        && legal.iter().any(in_called_suit)
+    {
+        return "keep the called suit".to_string();
+    }
+    if card.points() == 0 {
+        "throw off a low card".to_string()
+    } else {
+        format!("gives away {} points", card.points())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schafkopf_lib::schafkopf_env::game_logic::{Hand, PlayedCard, Suit};
+
+    /// Player `p` holds every card whose index is `p` modulo 4, so player 3 has the
+    /// Bells Ace and plays with the declarer, player 0.
+    fn striped_game() -> Game {
+        let mut game = Game::new(0);
+        for (player, hand) in game.hands.iter_mut().enumerate() {
+            *hand = Hand {
+                cards: Card::all().into_iter().skip(player).step_by(4).collect(),
+                played: Vec::new(),
+            };
+        }
+        game.contract = Contract::Call(Suit::Bells);
+        game.declarer = 0;
+        game
+    }
+
+    fn card(suit: Suit, value: Value) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_schmier_on_partners_trick() {
+        let mut game = striped_game();
+        game.play_card(card(Suit::Leaves, Value::Ten));
+        game.play_card(card(Suit::Leaves, Value::Eight));
+        game.play_card(card(Suit::Leaves, Value::Nine));
+        let state = game.get_player_game_state(3);
+        let hints = HintEngine::with_seed(2, 5).card_hints(&state, None);
+
+        let mut legal = game
+            .get_legal_actions(&game.hands[3])
+            .into_iter()
+            .map(|c| Action::Play(*c))
+            .collect::<Vec<Action>>();
+        let mut hinted = hints.iter().map(|h| h.action).collect::<Vec<Action>>();
+        let key = |a: &Action| match a {
+            Action::Play(card) => card.index(),
+            _ => usize::MAX,
+        };
+        legal.sort_by_key(key);
+        hinted.sort_by_key(key);
+        assert_eq!(hinted, legal);
+        assert!(hints
+            .windows(2)
+            .all(|w| w[0].expected_value >= w[1].expected_value));
+
+        let ace = hints
+            .iter()
+            .find(|h| h.action == Action::Play(card(Suit::Leaves, Value::Ace)))
+            .unwrap();
+        assert!(ace.reason.contains("schmier"));
+    }
+
+    #[test]
+    fn test_keep_the_called_suit() {
+        let game = striped_game();
+        let hand = Hand {
+            cards: vec![
+                card(Suit::Bells, Value::Seven),
+                card(Suit::Acorns, Value::Seven),
+            ],
+            played: Vec::new(),
+        };
+        let played = vec![PlayedCard(card(Suit::Leaves, Value::Ace), 3)];
+        let mut state = game.get_player_game_state(0);
+        state.hand = &hand;
+        state.played = &played;
+        let legal = hand.cards.clone();
+        assert_eq!(
+            card_reason(&state, &legal, card(Suit::Acorns, Value::Seven)),
+            "keep the called suit"
+        );
+    }
+
+    #[test]
+    fn test_bid_hints_include_pass() {
+        let game = striped_game();
+        let state = game.get_player_game_state(0);
+        let engine = HintEngine::with_seed(1, 0);
+        let mut auction = Auction::new(0);
+        let intents = engine.intent_hints(&state, &auction);
+        assert!(intents.iter().any(|h| h.action == Action::Intent(false)));
+
+        auction.announce_intent(true);
+        auction.announce_intent(true);
+        while !auction.bidding_phase_started() {
+            auction.announce_intent(false);
+        }
+        // once somebody has bid, the others may pass
+        let state = game.get_player_game_state(auction.next_bidder.unwrap());
+        auction.bid(auction.valid_bids(Some(state.hand))[0]);
+        let state = game.get_player_game_state(auction.next_bidder.unwrap());
+        let bids = engine.bid_hints(&state, &auction);
+        assert!(bids.iter().any(|h| h.action == Action::Bid(Contract::None)));
+        assert!(bids
+            .windows(2)
+            .all(|w| w[0].expected_value >= w[1].expected_value));
+    }
+}
@@ -0,0 +1,499 @@
+use crate::heuristic::HeuristicAgent;
+use crate::opponent::OpponentModel;
+use crate::sampler::{determinize, DealSampler};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use schafkopf_lib::schafkopf_env::agent::{Agent, RandomAgent};
+use schafkopf_lib::schafkopf_env::game_logic::{
+    Auction, Card, Contract, Game, PlayerGameState, RuleSet,
+};
+use std::time::{Duration, Instant};
+
+/// How much search an agent spends on a single decision.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+/// How the unseen cards are dealt at the start of every iteration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Determinization {
+    /// Respect everything revealed so far: hand sizes, voids and the called ace.
+    Constrained,
+    /// Only respect the hand sizes.
+    Unconstrained,
+}
+
+/// Policy used to finish the game once the search leaves the tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RolloutPolicy {
+    Random,
+    Heuristic,
+}
+
+#[derive(Clone, Debug)]
+pub struct IsmctsConfig {
+    pub budget: Budget,
+    /// UCT exploration constant, rewards are scaled to [0, 1].
+    pub exploration: f64,
+    pub determinization: Determinization,
+    pub rollout: RolloutPolicy,
+    /// Tariffs the bidding decisions are rewarded with.
+    pub rules: RuleSet,
+    /// How strongly a prior steers the search, fading with the number of visits.
+    pub prior_weight: f64,
+    /// Hard limit for a single decision, on top of the budget.
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> IsmctsConfig {
+        IsmctsConfig {
+            budget: Budget::Iterations(1000),
+            exploration: 0.7,
+            determinization: Determinization::Constrained,
+            rollout: RolloutPolicy::Heuristic,
+            rules: RuleSet::default(),
+            prior_weight: 1.0,
+            time_limit: None,
+        }
+    }
+}
+
+/// A decision of any kind, as stored in the search tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Intent(bool),
+    Bid(Contract),
+    Play(Card),
+}
+
+/// Prior knowledge about which actions are good, e.g. from a trained policy.
+pub trait Prior {
+    /// Probability of every action for the player to move, in the order given.
+    fn prior(
+        &self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+        actions: &[Action],
+    ) -> Vec<f64>;
+}
+
+/// Single-Observer Information Set Monte Carlo Tree Search agent.
+///
+/// The agent builds one tree over its own information sets. Every iteration deals the
+/// unseen cards anew and only descends into actions that are legal in that deal, so the
+/// statistics are shared between deals instead of being averaged after the fact as in PIMC.
+pub struct IsmctsAgent {
+    pub config: IsmctsConfig,
+    /// What the agent has learned about the other players so far.
+    pub opponents: OpponentModel,
+    /// The auction of the current game, as far as the agent has seen it.
+    auction: Option<Auction>,
+    rng: StdRng,
+    rollout: Box<dyn Agent>,
+    prior: Option<Box<dyn Prior>>,
+}
+
+struct Node {
+    action: Option<Action>,
+    /// Player who took the action leading to this node.
+    player: usize,
+    prior: f64,
+    visits: f64,
+    reward: f64,
+    available: f64,
+    children: Vec<usize>,
+}
+
+/// One determinization of the game, while bidding `auction` is still running.
+#[derive(Clone)]
+struct SimState {
+    game: Game,
+    auction: Option<Auction>,
+}
+
+impl IsmctsAgent {
+    pub fn new(config: IsmctsConfig) -> IsmctsAgent {
+        IsmctsAgent::with_rng(config, StdRng::from_entropy())
+    }
+
+    pub fn with_seed(config: IsmctsConfig, seed: u64) -> IsmctsAgent {
+        IsmctsAgent::with_rng(config, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(config: IsmctsConfig, mut rng: StdRng) -> IsmctsAgent {
+        let rollout: Box<dyn Agent> = match config.rollout {
+            RolloutPolicy::Random => Box::new(RandomAgent::with_seed(rng.gen())),
+            RolloutPolicy::Heuristic => Box::new(HeuristicAgent::new()),
+        };
+        IsmctsAgent {
+            config,
+            opponents: OpponentModel::new(),
+            auction: None,
+            rng,
+            rollout,
+            prior: None,
+        }
+    }
+
+    /// Expands the most promising actions first and biases the selection towards them.
+    pub fn with_prior(mut self, prior: Box<dyn Prior>) -> IsmctsAgent {
+        self.prior = Some(prior);
+        self
+    }
+
+    /// Runs the search from the player's point of view and returns the visit count of
+    /// every action available at the root.
+    pub fn search(
+        &mut self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+    ) -> Vec<(Action, usize)> {
+        let mut nodes = vec![Node {
+            action: None,
+            player: state.player_nr,
+            prior: 0.0,
+            visits: 0.0,
+            reward: 0.0,
+            available: 0.0,
+            children: Vec::new(),
+        }];
+        let bidding = auction.is_some();
+        let sampler = self.sampler(state);
+        let start = Instant::now();
+        let mut iterations = 0;
+
+        while !self.exhausted(start, iterations) {
+            iterations += 1;
+            let mut sim = self.determinize(&sampler, state, auction);
+            let mut path = vec![0];
+            let mut node = 0;
+
+            while let Some(player) = sim.to_move() {
+                let legal = sim.legal_actions();
+                let untried = legal
+                    .iter()
+                    .filter(|a| {
+                        !nodes[node]
+                            .children
+                            .iter()
+                            .any(|c| nodes[*c].action == Some(**a))
+                    })
+                    .copied()
+                    .collect::<Vec<Action>>();
+                if !untried.is_empty() {
+                    let (action, prior) = self.expand(&sim, player, &untried);
+                    nodes.push(Node {
+                        action: Some(action),
+                        player,
+                        prior,
+                        visits: 0.0,
+                        reward: 0.0,
+                        available: 1.0,
+                        children: Vec::new(),
+                    });
+                    let child = nodes.len() - 1;
+                    nodes[node].children.push(child);
+                    sim.apply(action);
+                    path.push(child);
+                    break;
+                }
+
+                let mut best = None;
+                let mut best_score = f64::NEG_INFINITY;
+                for index in nodes[node].children.clone() {
+                    let child = &mut nodes[index];
+                    let action = child.action.expect("Only the root has no action.");
+                    if !legal.contains(&action) {
+                        continue;
+                    }
+                    child.available += 1.0;
+                    let score = child.reward / child.visits
+                        + self.config.exploration * (child.available.ln() / child.visits).sqrt()
+                        + self.config.prior_weight * child.prior / (1.0 + child.visits);
+                    if score > best_score {
+                        best_score = score;
+                        best = Some((index, action));
+                    }
+                }
+                let (child, action) = best.expect("Every legal action has been tried.");
+                sim.apply(action);
+                path.push(child);
+                node = child;
+            }
+
+            self.play_out(&mut sim);
+            for index in path.into_iter().skip(1) {
+                let reward = self.reward(&sim, nodes[index].player, bidding);
+                nodes[index].visits += 1.0;
+                nodes[index].reward += reward;
+            }
+        }
+
+        nodes[0]
+            .children
+            .iter()
+            .map(|c| {
+                (
+                    nodes[*c].action.expect("Only the root has no action."),
+                    nodes[*c].visits as usize,
+                )
+            })
+            .collect()
+    }
+
+    /// Picks the untried action to expand, the one the prior likes most if there is one.
+    fn expand(&mut self, sim: &SimState, player: usize, untried: &[Action]) -> (Action, f64) {
+        match &self.prior {
+            Some(prior) => {
+                let state = sim.game.get_player_game_state(player);
+                let priors = prior.prior(&state, sim.auction.as_ref(), untried);
+                untried
+                    .iter()
+                    .copied()
+                    .zip(priors)
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .expect("There is an untried action.")
+            }
+            None => (
+                *untried
+                    .choose(&mut self.rng)
+                    .expect("There is an untried action."),
+                0.0,
+            ),
+        }
+    }
+
+    fn decide(&mut self, state: &PlayerGameState, auction: Option<&Auction>) -> Option<Action> {
+        self.search(state, auction)
+            .into_iter()
+            .max_by_key(|(_, visits)| *visits)
+            .map(|(action, _)| action)
+    }
+
+    fn exhausted(&self, start: Instant, iterations: usize) -> bool {
+        if iterations > 0 && self.config.time_limit.is_some_and(|l| start.elapsed() >= l) {
+            return true;
+        }
+        match self.config.budget {
+            Budget::Iterations(max) => iterations >= max.max(1),
+            Budget::Time(limit) => iterations > 0 && start.elapsed() >= limit,
+        }
+    }
+
+    fn sampler(&self, state: &PlayerGameState) -> DealSampler {
+        match self.config.determinization {
+            Determinization::Constrained => DealSampler::new(state)
+                .with_bidding(state, self.auction.as_ref().map(|a| &a.intent))
+                .with_weights(&self.opponents.deal_weights(state)),
+            Determinization::Unconstrained => DealSampler::unconstrained(state),
+        }
+    }
+
+    fn determinize(
+        &mut self,
+        sampler: &DealSampler,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+    ) -> SimState {
+        let hands = sampler.sample(&mut self.rng);
+        match auction {
+            Some(auction) => SimState {
+                game: Game::from_hands(state.forehand, hands),
+                auction: Some(auction.clone()),
+            },
+            None => SimState {
+                game: determinize(state, hands),
+                auction: None,
+            },
+        }
+    }
+
+    fn play_out(&mut self, sim: &mut SimState) {
+        while let Some(player) = sim.to_move() {
+            let state = sim.game.get_player_game_state(player);
+            let action = match &sim.auction {
+                Some(auction) if !auction.bidding_phase_started() => {
+                    Action::Intent(self.rollout.get_intent(&state, auction))
+                }
+                Some(auction) => Action::Bid(self.rollout.get_bid(&state, auction)),
+                None => {
+                    let legal = sim.game.get_legal_actions(&sim.game.hands[player]);
+                    Action::Play(self.rollout.get_play(&state, legal))
+                }
+            };
+            sim.apply(action);
+        }
+    }
+
+    /// Reward in [0, 1] of a finished game for `player`. Card play is rewarded with the
+    /// points of the player's side, bidding with the money won.
+    fn reward(&self, sim: &SimState, player: usize, bidding: bool) -> f64 {
+        let game = &sim.game;
+        if bidding {
+            let earnings = game.get_earnings(&self.config.rules)[player] as f64;
+            return 0.5 + 0.5 * (earnings / (3.0 * self.config.rules.solo_tariff as f64)).tanh();
+        }
+        let points = game.get_points();
+        if game.contract == Contract::Ramsch {
+            return 1.0 - points[player] as f64 / 120.0;
+        }
+        let team = game.declaring_team();
+        let side_points: u32 = (0..4)
+            .filter(|p| team[*p] == team[player])
+            .map(|p| points[p] as u32)
+            .sum();
+        side_points as f64 / 120.0
+    }
+}
+
+impl SimState {
+    fn to_move(&self) -> Option<usize> {
+        match &self.auction {
+            Some(auction) => auction.next_bidder,
+            None if self.game.is_over() => None,
+            None => Some(self.game.next_player),
+        }
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        match &self.auction {
+            Some(auction) if !auction.bidding_phase_started() => {
+                vec![Action::Intent(true), Action::Intent(false)]
+            }
+            Some(auction) => {
+                let bidder = auction
+                    .next_bidder
+                    .expect("Bidder must exist at this point.");
+                auction
+                    .valid_bids(Some(&self.game.hands[bidder]))
+                    .into_iter()
+                    .map(Action::Bid)
+                    .collect()
+            }
+            None => self
+                .game
+                .get_legal_actions(&self.game.hands[self.game.next_player])
+                .into_iter()
+                .map(|c| Action::Play(*c))
+                .collect(),
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        match (action, self.auction.as_mut()) {
+            (Action::Intent(intent), Some(auction)) => auction.announce_intent(intent),
+            (Action::Bid(bid), Some(auction)) => auction.bid(bid),
+            (Action::Play(card), None) => {
+                let played = self.game.play_card(card);
+                debug_assert!(played, "Only legal cards are offered.");
+            }
+            _ => panic!("Action {:?} doesn't fit the phase of the game.", action),
+        }
+        if let Some(auction) = &self.auction {
+            if auction.is_finished() {
+                self.game.contract = auction.winning_contract();
+                self.game.declarer = auction.highest_bidder;
+                self.auction = None;
+            }
+        }
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        self.auction = Some(auction.clone());
+        matches!(
+            self.decide(state, Some(auction)),
+            Some(Action::Intent(true))
+        )
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        self.auction = Some(auction.clone());
+        match self.decide(state, Some(auction)) {
+            Some(Action::Bid(bid)) => bid,
+            _ => auction.valid_bids(Some(state.hand))[0],
+        }
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        if legal_plays.len() == 1 {
+            return *legal_plays[0];
+        }
+        match self.decide(state, None) {
+            Some(Action::Play(card)) if legal_plays.contains(&&card) => card,
+            _ => *legal_plays[0],
+        }
+    }
+
+    fn game_over(&mut self, _player_nr: usize, auction: &Auction, game: &Game) {
+        self.opponents.observe(auction, game);
+        self.auction = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::seeded_game;
+    use schafkopf_lib::schafkopf_env::game_logic::{Suit, Value};
+
+    fn config(rollout: RolloutPolicy) -> IsmctsConfig {
+        IsmctsConfig {
+            budget: Budget::Iterations(200),
+            rollout,
+            ..IsmctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_plays_a_full_game() {
+        let mut agent = IsmctsAgent::with_seed(config(RolloutPolicy::Random), 5);
+        let mut game = seeded_game(2, 5);
+        game.contract = Contract::Wenz;
+        game.declarer = 3;
+        while !game.is_over() {
+            let player = game.next_player;
+            let state = game.get_player_game_state(player);
+            let legal = game.get_legal_actions(&game.hands[player]);
+            let card = agent.get_play(&state, legal.clone());
+            assert!(legal.contains(&&card));
+            game.play_card(card);
+        }
+    }
+
+    #[test]
+    fn test_bids_on_a_monster_hand() {
+        let cards = [
+            (Suit::Acorns, Value::Ober),
+            (Suit::Leaves, Value::Ober),
+            (Suit::Hearts, Value::Ober),
+            (Suit::Bells, Value::Ober),
+            (Suit::Acorns, Value::Under),
+            (Suit::Leaves, Value::Under),
+            (Suit::Hearts, Value::Ace),
+            (Suit::Hearts, Value::Ten),
+        ]
+        .map(|(suit, value)| Card { suit, value });
+        let rest = Card::all()
+            .into_iter()
+            .filter(|c| !cards.contains(c))
+            .collect::<Vec<Card>>();
+        let game = Game::from_hands(
+            0,
+            [
+                cards.to_vec(),
+                rest[0..8].to_vec(),
+                rest[8..16].to_vec(),
+                rest[16..24].to_vec(),
+            ],
+        );
+        let mut agent = IsmctsAgent::with_seed(config(RolloutPolicy::Heuristic), 9);
+        let auction = Auction::new(0);
+        assert!(agent.get_intent(&game.get_player_game_state(0), &auction));
+    }
+}
@@ -1,3 +1,20 @@
+pub mod analysis;
+pub mod bot;
+pub mod cfr;
+pub mod evaluation;
+pub mod heuristic;
+pub mod hint;
+pub mod ismcts;
+pub mod network;
+pub mod opponent;
+pub mod pimc;
+pub mod sampler;
+pub mod selfplay;
+pub mod simulation;
+pub mod solver;
+#[cfg(test)]
+mod testing;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
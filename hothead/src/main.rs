@@ -0,0 +1,38 @@
+use hothead::bot::{Bot, BotConfig, Level};
+use schafkopf_lib::schafkopf_env::console::{play_hand, TableLog};
+use schafkopf_lib::schafkopf_env::match_logic::Match;
+use std::{env, io, process};
+
+/// Plays against three hothead bots, e.g. `hothead expert`.
+fn main() -> io::Result<()> {
+    let level = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "intermediate".to_string());
+    let level = match level.parse::<Level>() {
+        Ok(level) => level,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+
+    let mut table = Match::new();
+    let seat = table.register_remote().expect("the match is empty");
+    for _ in 0..3 {
+        table
+            .register_player(Box::new(Bot::new(BotConfig::new(level))))
+            .expect("the match has free seats");
+    }
+    table.add_observer(Box::new(TableLog::new(io::stdout(), seat)));
+    println!(
+        "You are player {} against three {:?} bots. End the input (Ctrl-D) to quit.",
+        seat, level
+    );
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut output = io::stdout();
+    while play_hand(&mut table, seat, &mut input, &mut output)?.is_some() {}
+    table.stop();
+    Ok(())
+}
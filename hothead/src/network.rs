@@ -0,0 +1,480 @@
+use crate::ismcts::{Action, Prior};
+use rand::Rng;
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{Auction, Card, Contract, PlayerGameState};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"HHNN";
+const VERSION: u32 = 1;
+/// Most weights a layer in a file may have, far more than any real network needs.
+const MAX_WEIGHTS: usize = 1 << 24;
+
+/// Size of the encoded game state.
+pub const INPUTS: usize = 32 + 4 * 32 + 32 + CONTRACTS + 4 + 4 + 4;
+/// Size of the policy: one entry per card and per auction action.
+pub const POLICY: usize = 32 + 2 + 1 + 4 + 4 + 1;
+/// Size of the network output: the policy logits followed by the value.
+pub const OUTPUTS: usize = POLICY + 1;
+const CONTRACTS: usize = 11;
+
+/// A fully connected layer, `weights` holding one row of `inputs` weights per output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub bias: Vec<f32>,
+}
+
+impl Layer {
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        self.weights
+            .chunks(self.inputs)
+            .zip(self.bias.iter())
+            .map(|(row, bias)| bias + row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>())
+            .collect()
+    }
+}
+
+/// Multi-layer perceptron with ReLU activations between the layers.
+///
+/// Weights are stored in a small little-endian binary format: the magic `HHNN`, the format
+/// version and the number of layers as `u32`, then for every layer its input and output size
+/// as `u32` followed by the weights row by row and the biases as `f32`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    pub layers: Vec<Layer>,
+}
+
+impl Mlp {
+    /// A network with the given layer sizes and small random weights.
+    pub fn random<R: Rng>(sizes: &[usize], rng: &mut R) -> Mlp {
+        let layers = sizes
+            .windows(2)
+            .map(|size| {
+                let (inputs, outputs) = (size[0], size[1]);
+                let scale = (2.0 / inputs as f32).sqrt();
+                Layer {
+                    inputs,
+                    outputs,
+                    weights: (0..inputs * outputs)
+                        .map(|_| rng.gen_range(-1.0..1.0) * scale)
+                        .collect(),
+                    bias: vec![0.0; outputs],
+                }
+            })
+            .collect();
+        Mlp { layers }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.layers.first().map_or(0, |l| l.inputs)
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.layers.last().map_or(0, |l| l.outputs)
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut activation = input.to_vec();
+        for (i, layer) in self.layers.iter().enumerate() {
+            activation = layer.forward(&activation);
+            if i + 1 < self.layers.len() {
+                activation.iter_mut().for_each(|a| *a = a.max(0.0));
+            }
+        }
+        activation
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Mlp> {
+        Mlp::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a network from memory, e.g. from `include_bytes!` in a WASM build.
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Mlp> {
+        Mlp::read_from(&mut bytes)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.layers.len() as u32).to_le_bytes())?;
+        for layer in self.layers.iter() {
+            writer.write_all(&(layer.inputs as u32).to_le_bytes())?;
+            writer.write_all(&(layer.outputs as u32).to_le_bytes())?;
+            for value in layer.weights.iter().chain(layer.bias.iter()) {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Mlp> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a hothead network"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid("unsupported network version"));
+        }
+        let count = read_u32(reader)?;
+        let mut layers: Vec<Layer> = Vec::new();
+        for _ in 0..count {
+            let inputs = read_u32(reader)? as usize;
+            let outputs = read_u32(reader)? as usize;
+            if inputs == 0 || outputs == 0 {
+                return Err(invalid("empty layer"));
+            }
+            if layers.last().is_some_and(|l| l.outputs != inputs) {
+                return Err(invalid("layer sizes don't match"));
+            }
+            let size = inputs
+                .checked_mul(outputs)
+                .filter(|size| *size <= MAX_WEIGHTS)
+                .ok_or_else(|| invalid("layer too large"))?;
+            let weights = read_f32s(reader, size)?;
+            let bias = read_f32s(reader, outputs)?;
+            layers.push(Layer {
+                inputs,
+                outputs,
+                weights,
+                bias,
+            });
+        }
+        Ok(Mlp { layers })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32s<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<f32>> {
+    // sizes come from the file, so don't trust them before the values are there
+    let mut values = Vec::new();
+    let mut bytes = [0; 4];
+    for _ in 0..count {
+        reader.read_exact(&mut bytes)?;
+        values.push(f32::from_le_bytes(bytes));
+    }
+    Ok(values)
+}
+
+/// Index of an action in the policy output.
+pub fn action_index(action: &Action) -> usize {
+    match action {
+        Action::Play(card) => card.index(),
+        Action::Intent(false) => 32,
+        Action::Intent(true) => 33,
+        Action::Bid(Contract::Call(suit)) => 35 + *suit as usize,
+        Action::Bid(Contract::Solo(suit)) => 39 + *suit as usize,
+        Action::Bid(Contract::Wenz) => 43,
+        Action::Bid(_) => 34,
+    }
+}
+
+fn contract_index(contract: &Contract) -> usize {
+    match contract {
+        Contract::None => 0,
+        Contract::Ramsch => 1,
+        Contract::Wenz => 2,
+        Contract::Call(suit) => 3 + *suit as usize,
+        Contract::Solo(suit) => 7 + *suit as usize,
+    }
+}
+
+/// Encodes what the player knows, with all seats relative to the player.
+///
+/// The features are the own hand, the cards every seat has played, the current trick,
+/// the contract, the declarer, the seat relative to forehand and the announced intents.
+pub fn encode(state: &PlayerGameState, auction: Option<&Auction>) -> Vec<f32> {
+    let seat = |player: usize| (player + 4 - state.player_nr) % 4;
+    let mut input = vec![0.0; INPUTS];
+    for card in state.hand.cards.iter() {
+        input[card.index()] = 1.0;
+    }
+    for played in state.played.iter() {
+        input[32 + seat(played.1) * 32 + played.0.index()] = 1.0;
+    }
+    let offset = 32 + 4 * 32;
+    for played in state.played[state.played.len() / 4 * 4..].iter() {
+        input[offset + played.0.index()] = 1.0;
+    }
+    let offset = offset + 32;
+    input[offset + contract_index(&state.contract)] = 1.0;
+    let offset = offset + CONTRACTS;
+    if state.contract != Contract::None && state.contract != Contract::Ramsch {
+        input[offset + seat(state.declarer)] = 1.0;
+    }
+    let offset = offset + 4;
+    input[offset + (state.player_nr + 4 - state.forehand) % 4] = 1.0;
+    let offset = offset + 4;
+    if let Some(auction) = auction {
+        for (player, intent) in auction.intent.iter().enumerate() {
+            if *intent {
+                input[offset + seat(player)] = 1.0;
+            }
+        }
+    }
+    input
+}
+
+/// Policy and value predicted for a position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prediction {
+    /// Probability of every given action, in the same order.
+    pub policy: Vec<f32>,
+    /// Expected result for the player to move, from -1 (lost) to 1 (won).
+    pub value: f32,
+}
+
+/// Policy/value network on top of an [`Mlp`] with [`INPUTS`] inputs and [`OUTPUTS`] outputs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyValueNet {
+    pub mlp: Mlp,
+}
+
+impl PolicyValueNet {
+    pub fn new(mlp: Mlp) -> io::Result<PolicyValueNet> {
+        if mlp.inputs() != INPUTS || mlp.outputs() != OUTPUTS {
+            return Err(invalid("network doesn't fit the policy/value encoding"));
+        }
+        Ok(PolicyValueNet { mlp })
+    }
+
+    /// A randomly initialized network with the given hidden layer sizes.
+    pub fn random<R: Rng>(hidden: &[usize], rng: &mut R) -> PolicyValueNet {
+        let mut sizes = vec![INPUTS];
+        sizes.extend_from_slice(hidden);
+        sizes.push(OUTPUTS);
+        PolicyValueNet {
+            mlp: Mlp::random(&sizes, rng),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<PolicyValueNet> {
+        PolicyValueNet::new(Mlp::load(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<PolicyValueNet> {
+        PolicyValueNet::new(Mlp::from_bytes(bytes)?)
+    }
+
+    /// Predicts the policy over `actions`, a softmax over their logits, and the value.
+    pub fn predict(
+        &self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+        actions: &[Action],
+    ) -> Prediction {
+        let output = self.mlp.forward(&encode(state, auction));
+        let logits = actions
+            .iter()
+            .map(|a| output[action_index(a)])
+            .collect::<Vec<f32>>();
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps = logits.iter().map(|l| (l - max).exp()).collect::<Vec<f32>>();
+        let total: f32 = exps.iter().sum();
+        Prediction {
+            policy: exps.iter().map(|e| e / total).collect(),
+            value: output[POLICY].tanh(),
+        }
+    }
+
+    /// The action the network rates highest.
+    fn best(
+        &self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+        actions: &[Action],
+    ) -> Action {
+        let prediction = self.predict(state, auction, actions);
+        let best = prediction
+            .policy
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .expect("There is always an action to choose from.");
+        actions[best]
+    }
+}
+
+impl Prior for PolicyValueNet {
+    fn prior(
+        &self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+        actions: &[Action],
+    ) -> Vec<f64> {
+        self.predict(state, auction, actions)
+            .policy
+            .into_iter()
+            .map(|p| p as f64)
+            .collect()
+    }
+}
+
+/// Plays whatever the network rates highest.
+pub struct NetworkAgent {
+    pub net: PolicyValueNet,
+}
+
+impl NetworkAgent {
+    pub fn new(net: PolicyValueNet) -> NetworkAgent {
+        NetworkAgent { net }
+    }
+}
+
+impl Agent for NetworkAgent {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        let actions = [Action::Intent(false), Action::Intent(true)];
+        self.net.best(state, Some(auction), &actions) == Action::Intent(true)
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        let actions = auction
+            .valid_bids(Some(state.hand))
+            .into_iter()
+            .map(Action::Bid)
+            .collect::<Vec<Action>>();
+        match self.net.best(state, Some(auction), &actions) {
+            Action::Bid(bid) => bid,
+            _ => unreachable!("Only bids were offered."),
+        }
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        let actions = legal_plays
+            .iter()
+            .map(|c| Action::Play(**c))
+            .collect::<Vec<Action>>();
+        match self.net.best(state, None, &actions) {
+            Action::Play(card) => card,
+            _ => unreachable!("Only cards were offered."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ismcts::{Budget, IsmctsAgent, IsmctsConfig};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use schafkopf_lib::schafkopf_env::game_logic::Game;
+
+    fn net(seed: u64) -> PolicyValueNet {
+        PolicyValueNet::random(&[64, 32], &mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn test_action_indices_are_distinct() {
+        let mut actions = Card::all()
+            .into_iter()
+            .map(Action::Play)
+            .collect::<Vec<_>>();
+        actions.extend([Action::Intent(false), Action::Intent(true)]);
+        actions.extend(
+            Auction::new(0)
+                .valid_bids(None)
+                .into_iter()
+                .map(Action::Bid),
+        );
+        actions.push(Action::Bid(Contract::None));
+        let mut indices = actions.iter().map(action_index).collect::<Vec<usize>>();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices, (0..POLICY).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let net = net(1);
+        let mut bytes = Vec::new();
+        net.mlp.write_to(&mut bytes).unwrap();
+        let loaded = PolicyValueNet::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, net);
+        assert!(PolicyValueNet::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(PolicyValueNet::from_bytes(b"ONNX").is_err());
+        let mut huge = bytes[..12].to_vec();
+        huge.extend(u32::MAX.to_le_bytes());
+        huge.extend(u32::MAX.to_le_bytes());
+        assert!(PolicyValueNet::from_bytes(&huge).is_err());
+        let layer = |inputs: usize, outputs: usize| Layer {
+            inputs,
+            outputs,
+            weights: vec![0.0; inputs * outputs],
+            bias: vec![0.0; outputs],
+        };
+        let empty = Mlp {
+            layers: vec![layer(INPUTS, 0), layer(0, OUTPUTS)],
+        };
+        let mut bytes = Vec::new();
+        empty.write_to(&mut bytes).unwrap();
+        let error = PolicyValueNet::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.to_string(), "empty layer");
+
+        let game = Game::new(0);
+        let state = game.get_player_game_state(1);
+        let actions = [Action::Intent(false), Action::Intent(true)];
+        let prediction = loaded.predict(&state, Some(&Auction::new(0)), &actions);
+        assert!((prediction.policy.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(prediction.value.abs() <= 1.0);
+    }
+
+    #[test]
+    fn test_agent_and_prior_play_legal_games() {
+        let mut agent = NetworkAgent::new(net(2));
+        let config = IsmctsConfig {
+            budget: Budget::Iterations(50),
+            ..IsmctsConfig::default()
+        };
+        let mut search = IsmctsAgent::with_seed(config, 3).with_prior(Box::new(net(4)));
+        let mut game = Game::new(0);
+        let mut auction = Auction::new(0);
+        while let Some(player) = auction.next_bidder {
+            if auction.is_finished() {
+                break;
+            }
+            let state = game.get_player_game_state(player);
+            if auction.bidding_phase_started() {
+                let bid = agent.get_bid(&state, &auction);
+                assert!(auction.valid_bids(Some(&game.hands[player])).contains(&bid));
+                auction.bid(bid);
+            } else {
+                let intent = agent.get_intent(&state, &auction);
+                auction.announce_intent(intent);
+            }
+        }
+        game.contract = auction.winning_contract();
+        game.declarer = auction.highest_bidder;
+        while !game.is_over() {
+            let player = game.next_player;
+            let state = game.get_player_game_state(player);
+            let legal = game.get_legal_actions(&game.hands[player]);
+            let card = if player.is_multiple_of(2) {
+                agent.get_play(&state, legal.clone())
+            } else {
+                search.get_play(&state, legal.clone())
+            };
+            assert!(legal.contains(&&card));
+            game.play_card(card);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::evaluation::{bidding_position, HandEvaluator};
+use schafkopf_lib::schafkopf_env::game_logic::{
+    beats, is_trump, Auction, AuctionAction, Card, Contract, Game, PlayerGameState,
+};
+
+/// Number of imaginary games every rate starts with, so a handful of
+/// observations don't swing a profile from one extreme to the other.
+const PRIOR_GAMES: f64 = 8.0;
+const PRIOR_AGGRESSIVENESS: f64 = 0.3;
+const PRIOR_SCHMIER_RATE: f64 = 0.6;
+const PRIOR_TRUMP_LEAD_RATE: f64 = 0.4;
+/// Trumps a declarer holds relative to an average hand, typical for most players.
+const PRIOR_TRUMP_RATIO: f64 = 1.6;
+
+/// How a single player tends to bid and play, learned from finished games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub games: u32,
+    pub intent_chances: u32,
+    pub intents: u32,
+    pub schmier_chances: u32,
+    pub schmiers: u32,
+    pub leads: u32,
+    pub trump_leads: u32,
+    pub declared: u32,
+    /// Sum over the declared games of the trumps held relative to an average hand.
+    pub declared_trump_ratio: f64,
+}
+
+impl Profile {
+    /// How often the player announces the intent to play.
+    pub fn aggressiveness(&self) -> f64 {
+        smoothed(self.intents, self.intent_chances, PRIOR_AGGRESSIVENESS)
+    }
+
+    /// How often the player puts points on a trick their partner is winning.
+    pub fn schmier_rate(&self) -> f64 {
+        smoothed(self.schmiers, self.schmier_chances, PRIOR_SCHMIER_RATE)
+    }
+
+    /// How often the player leads a trump.
+    pub fn trump_lead_rate(&self) -> f64 {
+        smoothed(self.trump_leads, self.leads, PRIOR_TRUMP_LEAD_RATE)
+    }
+
+    /// How many trumps the player holds as declarer, relative to an average hand.
+    pub fn trump_ratio(&self) -> f64 {
+        (self.declared_trump_ratio + PRIOR_TRUMP_RATIO * PRIOR_GAMES)
+            / (self.declared as f64 + PRIOR_GAMES)
+    }
+
+    fn observe(&mut self, player: usize, auction: &Auction, game: &Game) {
+        self.games += 1;
+        for (bidder, action) in auction.history.iter() {
+            if let (true, AuctionAction::Intent(intent)) = (*bidder == player, action) {
+                self.intent_chances += 1;
+                self.intents += *intent as u32;
+            }
+        }
+
+        let contract = game.contract;
+        if player == game.declarer && contract != Contract::Ramsch {
+            let held = game
+                .played
+                .iter()
+                .filter(|p| p.1 == player && is_trump(&p.0, &contract))
+                .count();
+            let trumps = Card::all()
+                .iter()
+                .filter(|c| is_trump(c, &contract))
+                .count();
+            self.declared += 1;
+            self.declared_trump_ratio += held as f64 / (8.0 * trumps as f64 / 32.0);
+        }
+
+        let team = game.declaring_team();
+        for trick in game.played.chunks(4) {
+            if trick[0].1 == player {
+                self.leads += 1;
+                self.trump_leads += is_trump(&trick[0].0, &contract) as u32;
+            }
+            let mut winner = trick[0];
+            for played in trick.iter().skip(1) {
+                let partner_wins = contract != Contract::Ramsch && team[winner.1] == team[player];
+                if played.1 == player && partner_wins {
+                    self.schmier_chances += 1;
+                    self.schmiers += (played.0.points() >= 10) as u32;
+                }
+                if beats(&played.0, &winner.0, &contract) {
+                    winner = *played;
+                }
+            }
+        }
+    }
+}
+
+fn smoothed(count: u32, chances: u32, prior: f64) -> f64 {
+    (count as f64 + prior * PRIOR_GAMES) / (chances as f64 + PRIOR_GAMES)
+}
+
+/// Profiles of all four seats of a match, as seen by one player.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpponentModel {
+    pub profiles: [Profile; 4],
+}
+
+impl OpponentModel {
+    pub fn new() -> OpponentModel {
+        OpponentModel::default()
+    }
+
+    /// Learns from a finished game, every card is public at that point.
+    pub fn observe(&mut self, auction: &Auction, game: &Game) {
+        for (player, profile) in self.profiles.iter_mut().enumerate() {
+            profile.observe(player, auction, game);
+        }
+    }
+
+    /// Weights for the deal sampler, indexed by card and player.
+    ///
+    /// A declarer who only bids with many trumps gets more of them. A player who usually
+    /// leads trumps but hasn't done so in this game is probably short of them.
+    pub fn deal_weights(&self, state: &PlayerGameState) -> [[f64; 4]; 32] {
+        let contract = state.contract;
+        let mut trump_weight = [1.0; 4];
+        if contract != Contract::Ramsch && contract != Contract::None {
+            trump_weight[state.declarer] *=
+                self.profiles[state.declarer].trump_ratio() / PRIOR_TRUMP_RATIO;
+        }
+        for (player, weight) in trump_weight.iter_mut().enumerate() {
+            let leads = state.played.chunks(4).filter(|t| t[0].1 == player);
+            let (mut total, mut trumps) = (0, 0);
+            for trick in leads {
+                total += 1;
+                trumps += is_trump(&trick[0].0, &contract) as u32;
+            }
+            if total > 0 && trumps == 0 {
+                *weight *= 1.0 - 0.5 * self.profiles[player].trump_lead_rate();
+            }
+        }
+
+        let mut weights = [[1.0; 4]; 32];
+        for card in Card::all().iter().filter(|c| is_trump(c, &contract)) {
+            for (player, weight) in trump_weight.iter().enumerate() {
+                if player != state.player_nr {
+                    weights[card.index()][player] = *weight;
+                }
+            }
+        }
+        weights
+    }
+
+    /// Money the player should expect to lose to the other bidders in the auction.
+    /// A cautious player who announces the intent to play is worth taking seriously.
+    pub fn competition(&self, player: usize, auction: &Auction, evaluator: &HandEvaluator) -> f64 {
+        auction
+            .history
+            .iter()
+            .filter(|(bidder, action)| *bidder != player && *action == AuctionAction::Intent(true))
+            .map(|(bidder, _)| {
+                (1.0 - self.profiles[*bidder].aggressiveness()) * evaluator.rules.call_tariff as f64
+            })
+            .sum()
+    }
+
+    /// Announces the intent to play if the best contract still makes money
+    /// once the competition is taken into account.
+    pub fn intent(
+        &self,
+        evaluator: &HandEvaluator,
+        state: &PlayerGameState,
+        auction: &Auction,
+    ) -> bool {
+        let competition = self.competition(state.player_nr, auction, evaluator);
+        evaluator
+            .evaluate_all(state.hand, bidding_position(state))
+            .first()
+            .is_some_and(|e| e.expected_value > competition)
+    }
+
+    /// Bids like the evaluator, but passes on contracts the competition makes unprofitable.
+    pub fn bid(
+        &self,
+        evaluator: &HandEvaluator,
+        state: &PlayerGameState,
+        auction: &Auction,
+    ) -> Contract {
+        let bid = evaluator.bid(state, auction);
+        if bid == Contract::None || !auction.valid_bids(None).contains(&Contract::None) {
+            return bid;
+        }
+        let competition = self.competition(state.player_nr, auction, evaluator);
+        let estimate = evaluator.evaluate(state.hand, bid, bidding_position(state));
+        if estimate.expected_value > competition {
+            bid
+        } else {
+            Contract::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::HeuristicAgent;
+    use crate::testing::seeded_game;
+    use schafkopf_lib::schafkopf_env::agent::Agent;
+
+    /// Always wants to play and leads trumps whenever possible.
+    struct Eager(HeuristicAgent);
+
+    impl Agent for Eager {
+        fn get_intent(&mut self, _state: &PlayerGameState, _auction: &Auction) -> bool {
+            true
+        }
+
+        fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+            auction.valid_bids(Some(state.hand))[0]
+        }
+
+        fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+            match legal_plays.iter().find(|c| is_trump(c, &state.contract)) {
+                Some(card) if state.played.len().is_multiple_of(4) => **card,
+                _ => self.0.get_play(state, legal_plays),
+            }
+        }
+    }
+
+    fn play(agents: &mut [Box<dyn Agent>], forehand: usize, seed: u64) -> (Auction, Game) {
+        let mut game = seeded_game(forehand, seed);
+        let mut auction = Auction::new(forehand);
+        while !auction.is_finished() {
+            let player = auction.next_bidder.unwrap();
+            let state = game.get_player_game_state(player);
+            if auction.bidding_phase_started() {
+                let bid = agents[player].get_bid(&state, &auction);
+                auction.bid(bid);
+            } else {
+                let intent = agents[player].get_intent(&state, &auction);
+                auction.announce_intent(intent);
+            }
+        }
+        game.contract = auction.winning_contract();
+        game.declarer = auction.highest_bidder;
+        while !game.is_over() {
+            let player = game.next_player;
+            let state = game.get_player_game_state(player);
+            let legal = game.get_legal_actions(&game.hands[player]);
+            let card = agents[player].get_play(&state, legal);
+            game.play_card(card);
+        }
+        (auction, game)
+    }
+
+    #[test]
+    fn test_profiles_tell_styles_apart() {
+        let mut agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(Eager(HeuristicAgent::new())),
+            Box::new(HeuristicAgent::new()),
+            Box::new(HeuristicAgent::new()),
+            Box::new(HeuristicAgent::new()),
+        ];
+        let mut model = OpponentModel::new();
+        for hand in 0..40 {
+            let (auction, game) = play(&mut agents, hand % 4, hand as u64);
+            model.observe(&auction, &game);
+        }
+        let [eager, calm, ..] = &model.profiles;
+        assert_eq!(eager.games, 40);
+        assert!(eager.aggressiveness() > 0.8);
+        assert!(calm.aggressiveness() < eager.aggressiveness());
+        assert!(eager.trump_lead_rate() > calm.trump_lead_rate());
+        assert!(eager.schmier_chances + calm.schmier_chances > 0);
+    }
+
+    #[test]
+    fn test_cautious_bidders_scare_off_weak_hands() {
+        let mut model = OpponentModel::new();
+        model.profiles[1].intent_chances = 100;
+        model.profiles[2].intent_chances = 100;
+        model.profiles[2].intents = 100;
+        let evaluator = HandEvaluator::default();
+        let mut cautious = Auction::new(1);
+        cautious.announce_intent(true);
+        let mut reckless = Auction::new(2);
+        reckless.announce_intent(true);
+        assert!(
+            model.competition(0, &cautious, &evaluator)
+                > model.competition(0, &reckless, &evaluator)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::evaluation::HandEvaluator;
+use crate::heuristic::HeuristicAgent;
+use crate::opponent::OpponentModel;
+use crate::sampler::{determinize, DealSampler};
+use crate::solver::Solver;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{Auction, Card, Contract, Game, PlayerGameState};
+use std::time::{Duration, Instant};
+
+/// Perfect Information Monte Carlo agent.
+///
+/// For every card decision the agent deals the unseen cards a number of times, solves each
+/// deal with the double-dummy solver and plays the card with the best average result.
+/// Bidding is left to the heuristic hand evaluator.
+pub struct PimcAgent {
+    /// Number of deals sampled per decision.
+    pub samples: usize,
+    /// Stops sampling once a decision has taken this long. If not even one deal
+    /// could be solved in time, the agent plays like the heuristic agent.
+    pub time_limit: Option<Duration>,
+    /// What the agent has learned about the other players so far.
+    pub opponents: OpponentModel,
+    /// The auction of the current game, as far as the agent has seen it.
+    auction: Option<Auction>,
+    evaluator: HandEvaluator,
+    solver: Solver,
+    fallback: HeuristicAgent,
+    rng: StdRng,
+}
+
+impl PimcAgent {
+    pub fn new(samples: usize) -> PimcAgent {
+        PimcAgent::with_rng(samples, StdRng::from_entropy())
+    }
+
+    pub fn with_seed(samples: usize, seed: u64) -> PimcAgent {
+        PimcAgent::with_rng(samples, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(samples: usize, rng: StdRng) -> PimcAgent {
+        PimcAgent {
+            samples,
+            time_limit: None,
+            opponents: OpponentModel::new(),
+            auction: None,
+            evaluator: HandEvaluator::default(),
+            solver: Solver::new(),
+            fallback: HeuristicAgent::new(),
+            rng,
+        }
+    }
+
+    /// Tells the agent how the current game was bid, e.g. when it only advises a player
+    /// and didn't take part in the auction itself.
+    pub fn set_auction(&mut self, auction: Option<Auction>) {
+        self.auction = auction;
+    }
+
+    /// Average card points the player's side ends up with for every legal card,
+    /// taken over `samples` deals of the unseen cards. In a Ramsch that side is the other three.
+    /// Empty if the time limit didn't allow solving a single deal.
+    pub fn card_values(&mut self, state: &PlayerGameState) -> Vec<(Card, f64)> {
+        self.solver.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let sampler = DealSampler::new(state)
+            .with_bidding(state, self.auction.as_ref().map(|a| &a.intent))
+            .with_weights(&self.opponents.deal_weights(state));
+        let mut totals: Vec<(Card, f64)> = Vec::new();
+        let mut solved = 0;
+        for _ in 0..self.samples.max(1) {
+            if self.solver.deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+            let hands = sampler.sample(&mut self.rng);
+            let game = determinize(state, hands);
+            let (result, own_side) = match game.contract {
+                // everybody plays for themselves, so the player's side is everyone else
+                Contract::Ramsch => (self.solver.solve_ramsch(&game, state.player_nr), false),
+                _ => {
+                    let team = game.declaring_team();
+                    (self.solver.solve(&game, team), team[state.player_nr])
+                }
+            };
+            if self.solver.timed_out() {
+                break;
+            }
+            solved += 1;
+            for (card, value) in result.card_values {
+                let value = if own_side {
+                    value as f64
+                } else {
+                    120.0 - value as f64
+                };
+                match totals.iter_mut().find(|(c, _)| *c == card) {
+                    Some(total) => total.1 += value,
+                    None => totals.push((card, value)),
+                }
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(card, total)| (card, total / solved as f64))
+            .collect()
+    }
+}
+
+impl Agent for PimcAgent {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        self.auction = Some(auction.clone());
+        self.opponents.intent(&self.evaluator, state, auction)
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        self.auction = Some(auction.clone());
+        self.opponents.bid(&self.evaluator, state, auction)
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        if legal_plays.len() == 1 {
+            return *legal_plays[0];
+        }
+        let best = self
+            .card_values(state)
+            .into_iter()
+            .filter(|(card, _)| legal_plays.contains(&card))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(card, _)| card);
+        match best {
+            Some(card) => card,
+            None => self.fallback.get_play(state, legal_plays),
+        }
+    }
+
+    fn game_over(&mut self, _player_nr: usize, auction: &Auction, game: &Game) {
+        self.opponents.observe(auction, game);
+        self.auction = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schafkopf_lib::schafkopf_env::game_logic::{Suit, Value};
+
+    #[test]
+    fn test_plays_legal_cards() {
+        let mut agent = PimcAgent::with_seed(4, 11);
+        let mut game = Game::new(0);
+        game.contract = Contract::Call(Suit::Bells);
+        game.declarer = game
+            .hands
+            .iter()
+            .position(|h| {
+                !h.cards.contains(&Card {
+                    suit: Suit::Bells,
+                    value: Value::Ace,
+                })
+            })
+            .unwrap();
+        // skip the expensive first tricks
+        while game.played.len() < 12 {
+            let card = *game.get_legal_actions(&game.hands[game.next_player])[0];
+            game.play_card(card);
+        }
+        while !game.is_over() {
+            let player = game.next_player;
+            let state = game.get_player_game_state(player);
+            let legal = game.get_legal_actions(&game.hands[player]);
+            let card = agent.get_play(&state, legal.clone());
+            assert!(legal.contains(&&card));
+            game.play_card(card);
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use schafkopf_lib::schafkopf_env::game_logic::{
+    effective_suit, is_trump, Card, Contract, Game, PlayerGameState, Value,
+};
+
+/// Weight with which a bidder is assumed to hold trumps, relative to everybody else.
+const BIDDER_TRUMP_WEIGHT: f64 = 1.6;
+/// Weight with which a soloist is assumed to hold trumps.
+const SOLOIST_TRUMP_WEIGHT: f64 = 2.5;
+
+/// Deals the cards a player can't see to the other three players.
+///
+/// Every sample is consistent with what is public: the number of cards every player
+/// holds, the suits they have shown to be void in, who can and can't have the called
+/// ace and what the bidding revealed. Instead of rejecting inconsistent deals the cards
+/// are placed one by one, and a card only goes to a player if the rest of the cards can
+/// still be placed afterwards, so sampling never has to retry.
+///
+/// Optional weights bias who gets which card, e.g. from a bidding or opponent model.
+#[derive(Clone, Debug)]
+pub struct DealSampler {
+    player: usize,
+    own: Vec<Card>,
+    unseen: Vec<Card>,
+    sizes: [usize; 4],
+    /// For every card index, which players may hold the card.
+    allowed: [[bool; 4]; 32],
+    /// For every card index, how likely every player holds the card.
+    weights: [[f64; 4]; 32],
+    /// A player who holds at least one of the cards.
+    holds_one_of: Option<(usize, Vec<Card>)>,
+}
+
+impl DealSampler {
+    pub fn new(state: &PlayerGameState) -> DealSampler {
+        let mut sampler = DealSampler::unconstrained(state);
+        sampler.infer_voids(state);
+        sampler.infer_called_ace(state);
+        if !sampler.is_feasible() {
+            // can only happen if the rules were broken, don't trust anything then
+            return DealSampler::unconstrained(state);
+        }
+        sampler
+    }
+
+    /// A sampler that only respects the number of cards every player holds.
+    pub fn unconstrained(state: &PlayerGameState) -> DealSampler {
+        let unseen = Card::all()
+            .into_iter()
+            .filter(|c| !state.hand.cards.contains(c) && !state.played.iter().any(|p| p.0 == *c))
+            .collect::<Vec<Card>>();
+        let mut sizes = hand_sizes(state);
+        sizes[state.player_nr] = 0;
+        DealSampler {
+            player: state.player_nr,
+            own: state.hand.cards.clone(),
+            unseen,
+            sizes,
+            allowed: [[true; 4]; 32],
+            weights: [[1.0; 4]; 32],
+            holds_one_of: None,
+        }
+    }
+
+    /// Multiplies the weight of every (card, player) pair, indexed by `Card::index()`.
+    pub fn with_weights(mut self, weights: &[[f64; 4]; 32]) -> DealSampler {
+        for (own, other) in self.weights.iter_mut().zip(weights.iter()) {
+            for player in 0..4 {
+                own[player] *= other[player].max(0.0);
+            }
+        }
+        self
+    }
+
+    /// Biases the deal towards what the bidding suggests: whoever announced the intent to
+    /// play or won the auction is likely to hold more trumps than the others.
+    /// `intent` is the intent announced by every player, if known.
+    pub fn with_bidding(self, state: &PlayerGameState, intent: Option<&[bool; 4]>) -> DealSampler {
+        let contract = state.contract;
+        let mut weights = [[1.0; 4]; 32];
+        for card in Card::all().iter().filter(|c| is_trump(c, &contract)) {
+            for player in 0..4 {
+                if player == state.declarer && contract != Contract::Ramsch {
+                    weights[card.index()][player] = match contract {
+                        Contract::Call(_) => BIDDER_TRUMP_WEIGHT,
+                        _ => SOLOIST_TRUMP_WEIGHT,
+                    };
+                } else if intent.is_some_and(|i| i[player]) {
+                    weights[card.index()][player] = BIDDER_TRUMP_WEIGHT;
+                }
+            }
+        }
+        self.with_weights(&weights)
+    }
+
+    /// Marks that `player` can't hold `card`. Ignored if the deal would become impossible.
+    pub fn exclude(&mut self, player: usize, card: Card) {
+        let before = self.allowed[card.index()][player];
+        self.allowed[card.index()][player] = false;
+        if !self.is_feasible() {
+            self.allowed[card.index()][player] = before;
+        }
+    }
+
+    /// Whether there is at least one deal that satisfies all constraints.
+    pub fn is_feasible(&self) -> bool {
+        let remaining = self.unseen.iter().collect::<Vec<&Card>>();
+        self.fits(&remaining, &self.sizes)
+    }
+
+    /// Returns the current hands of all four players, including the player's own.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> [Vec<Card>; 4] {
+        let mut hands: [Vec<Card>; 4] = Default::default();
+        let mut capacity = self.sizes;
+        let mut remaining = self.unseen.iter().collect::<Vec<&Card>>();
+        // place the most constrained cards first
+        remaining.sort_by_key(|c| self.allowed[c.index()].iter().filter(|a| **a).count());
+
+        if let Some((player, cards)) = &self.holds_one_of {
+            let candidates = cards
+                .iter()
+                .filter(|c| self.allowed[c.index()][*player])
+                .filter(|c| {
+                    let rest = remaining
+                        .iter()
+                        .filter(|r| **r != *c)
+                        .copied()
+                        .collect::<Vec<&Card>>();
+                    let mut left = capacity;
+                    left[*player] -= 1;
+                    self.fits(&rest, &left)
+                })
+                .collect::<Vec<&Card>>();
+            if let Ok(card) = candidates.choose_weighted(rng, |c| self.weights[c.index()][*player])
+            {
+                capacity[*player] -= 1;
+                hands[*player].push(**card);
+                remaining.retain(|r| r != card);
+            }
+        }
+
+        while let Some(card) = remaining.first().copied() {
+            remaining.remove(0);
+            let candidates = (0..4)
+                .filter(|p| capacity[*p] > 0 && self.allowed[card.index()][*p])
+                .filter(|p| {
+                    let mut left = capacity;
+                    left[*p] -= 1;
+                    self.fits(&remaining, &left)
+                })
+                .collect::<Vec<usize>>();
+            let player = *candidates
+                .choose_weighted(rng, |p| {
+                    capacity[*p] as f64 * self.weights[card.index()][*p].max(f64::MIN_POSITIVE)
+                })
+                .expect("A feasible deal always has a player for the next card.");
+            capacity[player] -= 1;
+            hands[player].push(*card);
+        }
+        hands[self.player] = self.own.clone();
+        hands
+    }
+
+    /// Hall's condition: the cards fit if for every group of players the cards only
+    /// they may hold don't exceed the room they have left.
+    fn fits(&self, cards: &[&Card], capacity: &[usize; 4]) -> bool {
+        for group in 0..16usize {
+            let room: usize = (0..4)
+                .filter(|p| group & (1 << p) != 0)
+                .map(|p| capacity[p])
+                .sum();
+            let needed = cards
+                .iter()
+                .filter(|c| {
+                    (0..4).all(|p| {
+                        !(self.allowed[c.index()][p] && capacity[p] > 0) || group & (1 << p) != 0
+                    })
+                })
+                .count();
+            if needed > room {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn infer_voids(&mut self, state: &PlayerGameState) {
+        let contract = state.contract;
+        for trick in state.played.chunks(4) {
+            let lead_suit = effective_suit(&trick[0].0, &contract);
+            for played in trick.iter().skip(1) {
+                if effective_suit(&played.0, &contract) != lead_suit {
+                    // failed to follow -> void in the led suit
+                    for card in Card::all() {
+                        if effective_suit(&card, &contract) == lead_suit {
+                            self.allowed[card.index()][played.1] = false;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn infer_called_ace(&mut self, state: &PlayerGameState) {
+        let contract = state.contract;
+        let suit = match contract {
+            Contract::Call(suit) => suit,
+            _ => return,
+        };
+        let ace = Card {
+            suit,
+            value: Value::Ace,
+        };
+        // the declarer can't call an ace they hold
+        self.allowed[ace.index()][state.declarer] = false;
+        // the ace has to be given the first time its suit is led, later it may be kept
+        let first = state
+            .played
+            .chunks(4)
+            .find(|trick| effective_suit(&trick[0].0, &contract) == Some(suit));
+        if let Some(trick) = first.filter(|trick| !trick.iter().any(|p| p.0 == ace)) {
+            // whoever followed without the ace doesn't have it
+            for played in trick.iter().skip(1) {
+                self.allowed[ace.index()][played.1] = false;
+            }
+            if trick.len() == 4 {
+                // nobody gave it, so the leader ran away with it
+                for player in 0..4 {
+                    self.allowed[ace.index()][player] = player == trick[0].1;
+                }
+            }
+        }
+        // the declarer needs a card of the called suit to call it
+        let shown = state
+            .played
+            .iter()
+            .any(|p| p.1 == state.declarer && effective_suit(&p.0, &contract) == Some(suit));
+        if !shown && state.declarer != self.player {
+            let cards = self
+                .unseen
+                .iter()
+                .filter(|c| effective_suit(c, &contract) == Some(suit))
+                .copied()
+                .collect();
+            self.holds_one_of = Some((state.declarer, cards));
+        }
+    }
+}
+
+/// Rebuilds the game the player is in from sampled current hands.
+pub fn determinize(state: &PlayerGameState, hands: [Vec<Card>; 4]) -> Game {
+    let mut initial = hands;
+    for played in state.played.iter() {
+        initial[played.1].push(played.0);
+    }
+    let mut game = Game::from_hands(state.forehand, initial);
+    game.contract = state.contract;
+    game.declarer = state.declarer;
+    for played in state.played.iter() {
+        game.play_card(played.0);
+    }
+    game
+}
+
+/// Number of cards every player still holds.
+pub fn hand_sizes(state: &PlayerGameState) -> [usize; 4] {
+    let mut sizes = [8; 4];
+    for played in state.played.iter() {
+        sizes[played.1] -= 1;
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use schafkopf_lib::schafkopf_env::game_logic::Suit;
+
+    fn play_until(game: &mut Game, cards: usize) {
+        while game.played.len() < cards {
+            let card = *game.get_legal_actions(&game.hands[game.next_player])[0];
+            game.play_card(card);
+        }
+    }
+
+    #[test]
+    fn test_sample_respects_voids_and_sizes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for cards in [5, 13, 21, 27] {
+            for _ in 0..25 {
+                let mut game = Game::new(1);
+                game.contract = Contract::Solo(Suit::Leaves);
+                game.declarer = 2;
+                play_until(&mut game, cards);
+                let state = game.get_player_game_state(0);
+                let sampler = DealSampler::new(&state);
+                let hands = sampler.sample(&mut rng);
+                for (player, hand) in hands.iter().enumerate() {
+                    assert_eq!(hand.len(), game.hands[player].cards.len());
+                    assert!(hand
+                        .iter()
+                        .all(|c| sampler.allowed[c.index()][player] || player == 0));
+                }
+                let sampled = determinize(&state, hands);
+                assert_eq!(sampled.played, game.played);
+            }
+        }
+    }
+
+    #[test]
+    fn test_call_declarer_keeps_a_called_suit_card() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // every player holds two values of every suit, only three Bells are unseen
+        let mut hands: [Vec<Card>; 4] = Default::default();
+        for card in Card::all() {
+            hands[card.index() % 4].push(card);
+        }
+        let mut game = Game::from_hands(0, hands);
+        let declarer = 1;
+        game.contract = Contract::Call(Suit::Bells);
+        game.declarer = declarer;
+        let state = game.get_player_game_state(0);
+        let sampler = DealSampler::new(&state);
+        for _ in 0..100 {
+            let hands = sampler.sample(&mut rng);
+            assert!(hands[declarer]
+                .iter()
+                .any(|c| effective_suit(c, &game.contract) == Some(Suit::Bells)));
+            assert!(!hands[declarer].contains(&Card {
+                suit: Suit::Bells,
+                value: Value::Ace
+            }));
+        }
+    }
+
+    #[test]
+    fn test_tight_constraints_are_always_satisfied() {
+        // the last player is known to hold no trumps
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut game = Game::new(0);
+        game.contract = Contract::Wenz;
+        game.declarer = 0;
+        let state = game.get_player_game_state(0);
+        let mut sampler = DealSampler::new(&state);
+        for card in sampler.unseen.clone() {
+            if is_trump(&card, &Contract::Wenz) {
+                sampler.exclude(3, card);
+            }
+        }
+        assert!(sampler.is_feasible());
+        for _ in 0..100 {
+            let hands = sampler.sample(&mut rng);
+            assert!(!hands[3].iter().any(|c| is_trump(c, &Contract::Wenz)));
+            assert_eq!(hands[3].len(), 8);
+        }
+    }
+
+    #[test]
+    fn test_weights_bias_the_deal() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut game = Game::new(0);
+        game.contract = Contract::Solo(Suit::Hearts);
+        game.declarer = 1;
+        let state = game.get_player_game_state(0);
+        let plain = DealSampler::new(&state);
+        let biased = DealSampler::new(&state).with_bidding(&state, None);
+        let trumps = |hands: &[Vec<Card>; 4]| {
+            hands[1]
+                .iter()
+                .filter(|c| is_trump(c, &Contract::Solo(Suit::Hearts)))
+                .count()
+        };
+        let plain_trumps: usize = (0..200).map(|_| trumps(&plain.sample(&mut rng))).sum();
+        let biased_trumps: usize = (0..200).map(|_| trumps(&biased.sample(&mut rng))).sum();
+        assert!(biased_trumps > plain_trumps);
+    }
+
+    #[test]
+    fn test_partner_who_ran_away_keeps_the_ace() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let cards = |suit: Suit, values: &[Value]| {
+            values
+                .iter()
+                .map(|value| Card {
+                    suit,
+                    value: *value,
+                })
+                .collect::<Vec<Card>>()
+        };
+        let all = [Suit::Bells, Suit::Hearts, Suit::Leaves, Suit::Acorns];
+        let hands = [
+            [
+                cards(
+                    Suit::Bells,
+                    &[Value::Ace, Value::King, Value::Nine, Value::Eight],
+                ),
+                all.iter().flat_map(|s| cards(*s, &[Value::Ober])).collect(),
+            ]
+            .concat(),
+            [
+                cards(Suit::Bells, &[Value::Ten, Value::Seven]),
+                cards(Suit::Hearts, &[Value::Ace, Value::Ten]),
+                all.iter()
+                    .flat_map(|s| cards(*s, &[Value::Under]))
+                    .collect(),
+            ]
+            .concat(),
+            [
+                cards(
+                    Suit::Acorns,
+                    &[Value::Ace, Value::Ten, Value::King, Value::Nine],
+                ),
+                cards(
+                    Suit::Hearts,
+                    &[Value::King, Value::Nine, Value::Eight, Value::Seven],
+                ),
+            ]
+            .concat(),
+            [
+                cards(Suit::Acorns, &[Value::Eight, Value::Seven]),
+                cards(
+                    Suit::Leaves,
+                    &[
+                        Value::Ace,
+                        Value::Ten,
+                        Value::King,
+                        Value::Nine,
+                        Value::Eight,
+                        Value::Seven,
+                    ],
+                ),
+            ]
+            .concat(),
+        ];
+        let mut game = Game::from_hands(0, hands);
+        game.contract = Contract::Call(Suit::Bells);
+        game.declarer = 1;
+        // the partner runs away with the ace, then keeps it when Bells are led again
+        for (suit, value) in [
+            (Suit::Bells, Value::King),
+            (Suit::Bells, Value::Ten),
+            (Suit::Acorns, Value::Nine),
+            (Suit::Leaves, Value::Seven),
+            (Suit::Bells, Value::Seven),
+            (Suit::Acorns, Value::King),
+            (Suit::Leaves, Value::Eight),
+            (Suit::Bells, Value::Nine),
+        ] {
+            assert!(game.play_card(Card { suit, value }));
+        }
+        let state = game.get_player_game_state(2);
+        let sampler = DealSampler::new(&state);
+        assert!(sampler.is_feasible());
+        let ace = Card {
+            suit: Suit::Bells,
+            value: Value::Ace,
+        };
+        for _ in 0..50 {
+            assert!(sampler.sample(&mut rng)[0].contains(&ace));
+        }
+    }
+}
@@ -0,0 +1,585 @@
+use crate::ismcts::Action;
+use crate::network::{action_index, encode, PolicyValueNet, INPUTS, POLICY};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{Auction, Card, Contract, PlayerGameState, RuleSet};
+use schafkopf_lib::schafkopf_env::match_logic::Match;
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+
+const MAGIC: &[u8; 4] = b"HHRB";
+const OBSERVATION_BYTES: usize = INPUTS.div_ceil(8);
+
+/// A single decision taken during self-play.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub player: usize,
+    /// Encoded state the player saw, see [`encode`].
+    pub observation: Vec<f32>,
+    /// Which policy entries were legal.
+    pub mask: [bool; POLICY],
+    /// Policy index of the chosen action.
+    pub action: usize,
+}
+
+/// All decisions of one game and how it was settled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    pub steps: Vec<Step>,
+    pub earnings: [i32; 4],
+}
+
+/// A step together with the earnings of the player who took it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub observation: Vec<f32>,
+    pub mask: [bool; POLICY],
+    pub action: usize,
+    pub earnings: i32,
+}
+
+/// Replay buffer on disk, an append-only file of samples.
+///
+/// Every sample is stored as the action (`u8`), the mask (`u64`), the observation
+/// packed into bits and the earnings (`i32`), all little-endian, after a `HHRB` header.
+pub struct ReplayBuffer {
+    path: PathBuf,
+}
+
+impl ReplayBuffer {
+    /// Opens the buffer at `path`, creating the file if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ReplayBuffer> {
+        let path = path.as_ref().to_path_buf();
+        if !path.exists() {
+            File::create(&path)?.write_all(MAGIC)?;
+        }
+        Ok(ReplayBuffer { path })
+    }
+
+    pub fn append(&mut self, trajectories: &[Trajectory]) -> io::Result<usize> {
+        let mut writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        let mut written = 0;
+        for trajectory in trajectories {
+            for step in trajectory.steps.iter() {
+                writer.write_all(&[step.action as u8])?;
+                let mask = step
+                    .mask
+                    .iter()
+                    .enumerate()
+                    .fold(0u64, |mask, (i, legal)| mask | ((*legal as u64) << i));
+                writer.write_all(&mask.to_le_bytes())?;
+                let mut packed = [0u8; OBSERVATION_BYTES];
+                for (i, value) in step.observation.iter().enumerate() {
+                    if *value != 0.0 {
+                        packed[i / 8] |= 1 << (i % 8);
+                    }
+                }
+                writer.write_all(&packed)?;
+                writer.write_all(&trajectory.earnings[step.player].to_le_bytes())?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Reads the newest `limit` samples.
+    pub fn read_latest(&self, limit: usize) -> io::Result<Vec<Sample>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a hothead replay buffer",
+            ));
+        }
+        let mut samples = Vec::new();
+        let mut record = [0u8; 1 + 8 + OBSERVATION_BYTES + 4];
+        loop {
+            match reader.read_exact(&mut record) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let mask_bits = u64::from_le_bytes(record[1..9].try_into().expect("8 bytes"));
+            let mut mask = [false; POLICY];
+            for (i, legal) in mask.iter_mut().enumerate() {
+                *legal = mask_bits & (1 << i) != 0;
+            }
+            let packed = &record[9..9 + OBSERVATION_BYTES];
+            let observation = (0..INPUTS)
+                .map(|i| ((packed[i / 8] >> (i % 8)) & 1) as f32)
+                .collect();
+            let earnings =
+                i32::from_le_bytes(record[9 + OBSERVATION_BYTES..].try_into().expect("4 bytes"));
+            samples.push(Sample {
+                observation,
+                mask,
+                action: record[0] as usize,
+                earnings,
+            });
+            if samples.len() > limit.saturating_mul(2) {
+                samples.drain(..samples.len() - limit);
+            }
+        }
+        if samples.len() > limit {
+            samples.drain(..samples.len() - limit);
+        }
+        Ok(samples)
+    }
+}
+
+/// A `Match` with `net` in all four seats, sampling every decision from its policy.
+pub struct SelfPlayTable {
+    table: Match,
+    shared: Rc<RefCell<Shared>>,
+}
+
+/// What the four seats of a table share: the network, the randomness and the record.
+struct Shared {
+    net: PolicyValueNet,
+    rng: StdRng,
+    steps: Vec<Step>,
+}
+
+/// One seat of a `SelfPlayTable`.
+struct PolicySeat(Rc<RefCell<Shared>>);
+
+impl SelfPlayTable {
+    pub fn new(net: PolicyValueNet, rules: RuleSet, seed: u64) -> SelfPlayTable {
+        let shared = Rc::new(RefCell::new(Shared {
+            net,
+            rng: StdRng::seed_from_u64(seed),
+            steps: Vec::new(),
+        }));
+        let mut table = Match::with_rules(rules);
+        for _ in 0..4 {
+            table
+                .register_player(Box::new(PolicySeat(shared.clone())))
+                .expect("a new match has four seats");
+        }
+        SelfPlayTable { table, shared }
+    }
+
+    /// Deals the next hand and plays it, the forehand moving on like in any match.
+    pub fn play_game(&mut self) -> Trajectory {
+        let mut cards = Card::all();
+        cards.shuffle(&mut self.shared.borrow_mut().rng);
+        let hands = [0, 1, 2, 3].map(|p| cards[p * 8..p * 8 + 8].to_vec());
+        let result = self
+            .table
+            .play_deal(hands)
+            .expect("all seats are played by the network");
+        Trajectory {
+            steps: std::mem::take(&mut self.shared.borrow_mut().steps),
+            earnings: result.earnings,
+        }
+    }
+}
+
+impl PolicySeat {
+    fn decide(
+        &mut self,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+        actions: &[Action],
+    ) -> Action {
+        let observation = encode(state, auction);
+        let Shared { net, rng, steps } = &mut *self.0.borrow_mut();
+        choose(net, observation, state.player_nr, actions, steps, rng)
+    }
+}
+
+impl Agent for PolicySeat {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        let actions = [Action::Intent(false), Action::Intent(true)];
+        match self.decide(state, Some(auction), &actions) {
+            Action::Intent(intent) => intent,
+            _ => unreachable!("Only intents were offered."),
+        }
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        let actions = auction
+            .valid_bids(Some(state.hand))
+            .into_iter()
+            .map(Action::Bid)
+            .collect::<Vec<Action>>();
+        match self.decide(state, Some(auction), &actions) {
+            Action::Bid(bid) => bid,
+            _ => unreachable!("Only bids were offered."),
+        }
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        let actions = legal_plays
+            .into_iter()
+            .map(|c| Action::Play(*c))
+            .collect::<Vec<Action>>();
+        match self.decide(state, None, &actions) {
+            Action::Play(card) => card,
+            _ => unreachable!("Only cards were offered."),
+        }
+    }
+}
+
+/// Samples one of `actions` from the policy and records the decision.
+fn choose<R: Rng>(
+    net: &PolicyValueNet,
+    observation: Vec<f32>,
+    player: usize,
+    actions: &[Action],
+    steps: &mut Vec<Step>,
+    rng: &mut R,
+) -> Action {
+    let output = net.mlp.forward(&observation);
+    let mut mask = [false; POLICY];
+    for action in actions {
+        mask[action_index(action)] = true;
+    }
+    let policy = masked_softmax(&output[..POLICY], &mask);
+    let action = *actions
+        .choose_weighted(rng, |a| policy[action_index(a)])
+        .unwrap_or(&actions[0]);
+    steps.push(Step {
+        player,
+        observation,
+        mask,
+        action: action_index(&action),
+    });
+    action
+}
+
+fn masked_softmax(logits: &[f32], mask: &[bool; POLICY]) -> [f32; POLICY] {
+    let max = logits
+        .iter()
+        .zip(mask)
+        .filter(|(_, legal)| **legal)
+        .map(|(l, _)| *l)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let mut policy = [0.0; POLICY];
+    let mut total = 0.0;
+    for (i, logit) in logits.iter().enumerate() {
+        if mask[i] {
+            policy[i] = (logit - max).exp();
+            total += policy[i];
+        }
+    }
+    policy.iter_mut().for_each(|p| *p /= total);
+    policy
+}
+
+/// Plain SGD on the policy-gradient loss with the value head as baseline, plus a squared
+/// error for the value head. Earnings are scaled by `earnings_scale` and clamped to [-1, 1].
+#[derive(Clone, Debug)]
+pub struct TrainingStep {
+    pub learning_rate: f32,
+    pub value_weight: f32,
+    pub earnings_scale: f32,
+}
+
+impl Default for TrainingStep {
+    fn default() -> TrainingStep {
+        TrainingStep {
+            learning_rate: 0.01,
+            value_weight: 0.5,
+            earnings_scale: 150.0,
+        }
+    }
+}
+
+impl TrainingStep {
+    /// Updates the network on one batch and returns the mean loss before the update.
+    pub fn apply(&self, net: &mut PolicyValueNet, batch: &[Sample]) -> f32 {
+        if batch.is_empty() {
+            return 0.0;
+        }
+        let layers = &net.mlp.layers;
+        let mut weight_grads = layers
+            .iter()
+            .map(|l| vec![0.0; l.weights.len()])
+            .collect::<Vec<Vec<f32>>>();
+        let mut bias_grads = layers
+            .iter()
+            .map(|l| vec![0.0; l.bias.len()])
+            .collect::<Vec<Vec<f32>>>();
+        let mut loss = 0.0;
+
+        for sample in batch {
+            // forward pass, keeping the input of every layer
+            let mut activations = vec![sample.observation.clone()];
+            for (i, layer) in layers.iter().enumerate() {
+                let mut output = layer.forward(activations.last().expect("Input is there."));
+                if i + 1 < layers.len() {
+                    output.iter_mut().for_each(|a| *a = a.max(0.0));
+                }
+                activations.push(output);
+            }
+            let output = activations.last().expect("Output is there.");
+
+            let target = (sample.earnings as f32 / self.earnings_scale).clamp(-1.0, 1.0);
+            let value = output[POLICY].tanh();
+            let advantage = target - value;
+            let policy = masked_softmax(&output[..POLICY], &sample.mask);
+            loss += -advantage * policy[sample.action].max(1e-8).ln()
+                + self.value_weight * (value - target).powi(2);
+
+            let mut grad = vec![0.0; output.len()];
+            for (i, g) in grad.iter_mut().take(POLICY).enumerate() {
+                if sample.mask[i] {
+                    let chosen = if i == sample.action { 1.0 } else { 0.0 };
+                    *g = advantage * (policy[i] - chosen);
+                }
+            }
+            grad[POLICY] = 2.0 * self.value_weight * (value - target) * (1.0 - value * value);
+
+            // backward pass
+            for (i, layer) in layers.iter().enumerate().rev() {
+                let input = &activations[i];
+                let mut input_grad = vec![0.0; layer.inputs];
+                for (o, g) in grad.iter().enumerate() {
+                    if *g == 0.0 {
+                        continue;
+                    }
+                    bias_grads[i][o] += g;
+                    let row = o * layer.inputs;
+                    for (j, x) in input.iter().enumerate() {
+                        weight_grads[i][row + j] += g * x;
+                        input_grad[j] += g * layer.weights[row + j];
+                    }
+                }
+                if i > 0 {
+                    // ReLU of the previous layer
+                    for (g, a) in input_grad.iter_mut().zip(input.iter()) {
+                        if *a <= 0.0 {
+                            *g = 0.0;
+                        }
+                    }
+                }
+                grad = input_grad;
+            }
+        }
+
+        let step = self.learning_rate / batch.len() as f32;
+        for (i, layer) in net.mlp.layers.iter_mut().enumerate() {
+            for (w, g) in layer.weights.iter_mut().zip(weight_grads[i].iter()) {
+                *w -= step * g;
+            }
+            for (b, g) in layer.bias.iter_mut().zip(bias_grads[i].iter()) {
+                *b -= step * g;
+            }
+        }
+        loss / batch.len() as f32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SelfPlayConfig {
+    /// Games played per iteration, split over the threads.
+    pub games: usize,
+    pub threads: usize,
+    /// Newest samples trained on per iteration.
+    pub window: usize,
+    pub batch_size: usize,
+    pub batches: usize,
+    /// Save a checkpoint every this many iterations.
+    pub checkpoint_every: usize,
+    /// Directory that holds the replay buffer and the checkpoints.
+    pub directory: PathBuf,
+    pub rules: RuleSet,
+    pub training: TrainingStep,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> SelfPlayConfig {
+        SelfPlayConfig {
+            games: 256,
+            threads: 4,
+            window: 50_000,
+            batch_size: 64,
+            batches: 100,
+            checkpoint_every: 10,
+            directory: PathBuf::from("selfplay"),
+            rules: RuleSet::default(),
+            training: TrainingStep::default(),
+        }
+    }
+}
+
+/// What happened during one self-play iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IterationReport {
+    pub iteration: usize,
+    pub games: usize,
+    pub samples: usize,
+    pub loss: f32,
+    pub checkpoint: Option<PathBuf>,
+}
+
+/// Self-play loop: play games with the current network, store them in the replay
+/// buffer, train on the newest samples and checkpoint every now and then.
+pub struct SelfPlay {
+    pub config: SelfPlayConfig,
+    pub net: PolicyValueNet,
+    pub iteration: usize,
+    buffer: ReplayBuffer,
+    rng: StdRng,
+}
+
+impl SelfPlay {
+    pub fn new(config: SelfPlayConfig, net: PolicyValueNet, seed: u64) -> io::Result<SelfPlay> {
+        fs::create_dir_all(&config.directory)?;
+        let buffer = ReplayBuffer::open(config.directory.join("replay.bin"))?;
+        Ok(SelfPlay {
+            config,
+            net,
+            iteration: 0,
+            buffer,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    pub fn checkpoint_path(&self, iteration: usize) -> PathBuf {
+        self.config
+            .directory
+            .join(format!("policy_{:06}.hhnn", iteration))
+    }
+
+    pub fn run_iteration(&mut self) -> io::Result<IterationReport> {
+        self.iteration += 1;
+        let trajectories = self.play_games();
+        let samples = self.buffer.append(&trajectories)?;
+
+        let window = self.buffer.read_latest(self.config.window)?;
+        let mut loss = 0.0;
+        for _ in 0..self.config.batches {
+            let batch = window
+                .choose_multiple(&mut self.rng, self.config.batch_size)
+                .cloned()
+                .collect::<Vec<Sample>>();
+            loss += self.config.training.apply(&mut self.net, &batch);
+        }
+
+        let checkpoint = if self
+            .iteration
+            .is_multiple_of(self.config.checkpoint_every.max(1))
+        {
+            let path = self.checkpoint_path(self.iteration);
+            self.net.mlp.save(&path)?;
+            Some(path)
+        } else {
+            None
+        };
+        Ok(IterationReport {
+            iteration: self.iteration,
+            games: trajectories.len(),
+            samples,
+            loss: loss / self.config.batches.max(1) as f32,
+            checkpoint,
+        })
+    }
+
+    fn play_games(&mut self) -> Vec<Trajectory> {
+        let threads = self.config.threads.max(1);
+        let seeds = (0..threads).map(|_| self.rng.gen()).collect::<Vec<u64>>();
+        let net = &self.net;
+        let rules = self.config.rules;
+        let games = self.config.games;
+        thread::scope(|scope| {
+            let handles = seeds
+                .into_iter()
+                .enumerate()
+                .map(|(t, seed)| {
+                    scope.spawn(move || {
+                        let mut table = SelfPlayTable::new(net.clone(), rules, seed);
+                        (t..games)
+                            .step_by(threads)
+                            .map(|_| table.play_game())
+                            .collect::<Vec<Trajectory>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("Self-play thread panicked."))
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("hothead_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_self_play_writes_buffer_and_checkpoints() {
+        let config = SelfPlayConfig {
+            games: 6,
+            threads: 2,
+            batch_size: 16,
+            batches: 2,
+            checkpoint_every: 2,
+            directory: directory("selfplay"),
+            ..SelfPlayConfig::default()
+        };
+        let net = PolicyValueNet::random(&[16], &mut StdRng::seed_from_u64(1));
+        let mut self_play = SelfPlay::new(config.clone(), net, 2).unwrap();
+        let first = self_play.run_iteration().unwrap();
+        assert_eq!(first.games, 6);
+        assert!(first.samples >= 6 * 32);
+        assert_eq!(first.checkpoint, None);
+        let second = self_play.run_iteration().unwrap();
+        let checkpoint = second.checkpoint.unwrap();
+        assert_eq!(PolicyValueNet::load(&checkpoint).unwrap(), self_play.net);
+        let samples = self_play.buffer.read_latest(usize::MAX).unwrap();
+        assert_eq!(samples.len(), first.samples + second.samples);
+        assert!(samples.iter().all(|s| s.mask[s.action]));
+        fs::remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn test_training_step_fits_a_batch() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut net = PolicyValueNet::random(&[16], &mut rng);
+        let mut table = SelfPlayTable::new(net.clone(), RuleSet::default(), 4);
+        let batch = (0..4)
+            .flat_map(|_| table.play_game().steps)
+            .take(32)
+            .map(|step| Sample {
+                observation: step.observation,
+                mask: step.mask,
+                action: step.action,
+                earnings: 100,
+            })
+            .collect::<Vec<Sample>>();
+        let training = TrainingStep {
+            value_weight: 1.0,
+            ..TrainingStep::default()
+        };
+        let value = |net: &PolicyValueNet| {
+            batch
+                .iter()
+                .map(|s| net.mlp.forward(&s.observation)[POLICY].tanh())
+                .sum::<f32>()
+        };
+        let before = value(&net);
+        for _ in 0..50 {
+            training.apply(&mut net, &batch);
+        }
+        assert!(value(&net) > before);
+    }
+}
@@ -0,0 +1,178 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::game_logic::{Card, Contract, RuleSet};
+use schafkopf_lib::schafkopf_env::match_logic::{HandResult, Match};
+use std::collections::HashMap;
+
+/// Creates an agent from a seed. Has to be shareable between threads, the agents it
+/// creates don't.
+pub type SeededFactory = Box<dyn Fn(u64) -> Box<dyn Agent> + Send + Sync>;
+
+/// How often a contract was played and how often the declaring side won it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContractTally {
+    pub played: usize,
+    /// Always 0 for Ramsch, which has no declaring side.
+    pub won: usize,
+}
+
+/// Outcome of a batch of simulated hands, per agent in the order of the factories.
+/// Only counts what the hand results show, `statistics::Statistics` in schafkopf_lib
+/// has the card points, Schneider and Schwarz of every contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationStats {
+    pub hands: usize,
+    pub contracts: HashMap<Contract, ContractTally>,
+    /// Hands every agent won money in.
+    pub wins: [usize; 4],
+    pub earnings: [i64; 4],
+    /// Contracts every agent declared.
+    pub declared: [usize; 4],
+}
+
+impl SimulationStats {
+    /// Adds a hand in which agent `agents[seat]` sat at `seat`.
+    pub fn add(&mut self, agents: [usize; 4], result: &HandResult) {
+        self.hands += 1;
+        let contract = self.contracts.entry(result.contract).or_default();
+        contract.played += 1;
+        if result.contract != Contract::Ramsch {
+            if result.earnings[result.declarer] > 0 {
+                contract.won += 1;
+            }
+            self.declared[agents[result.declarer]] += 1;
+        }
+        for (seat, agent) in agents.iter().enumerate() {
+            self.earnings[*agent] += result.earnings[seat] as i64;
+            if result.earnings[seat] > 0 {
+                self.wins[*agent] += 1;
+            }
+        }
+    }
+
+    pub fn merge(mut self, other: SimulationStats) -> SimulationStats {
+        self.hands += other.hands;
+        for (contract, stats) in other.contracts {
+            let entry = self.contracts.entry(contract).or_default();
+            entry.played += stats.played;
+            entry.won += stats.won;
+        }
+        for agent in 0..4 {
+            self.wins[agent] += other.wins[agent];
+            self.earnings[agent] += other.earnings[agent];
+            self.declared[agent] += other.declared[agent];
+        }
+        self
+    }
+
+    /// Share of the hands played as `contract`.
+    pub fn frequency(&self, contract: Contract) -> f64 {
+        let played = self.contracts.get(&contract).map_or(0, |c| c.played);
+        played as f64 / self.hands.max(1) as f64
+    }
+
+    pub fn win_rate(&self, agent: usize) -> f64 {
+        self.wins[agent] as f64 / self.hands.max(1) as f64
+    }
+
+    pub fn mean_earnings(&self, agent: usize) -> f64 {
+        self.earnings[agent] as f64 / self.hands.max(1) as f64
+    }
+}
+
+/// Plays `n_hands` independent hands on all cores.
+///
+/// Every hand is dealt and seeded from `seed` and its own number only, so the stats are the
+/// same for a seed whatever the number of threads, and different seeds deal different hands.
+/// The agents are created fresh for every hand and take turns at the forehand seat.
+pub fn simulate(
+    n_hands: usize,
+    agent_factories: &[SeededFactory; 4],
+    rule_set: RuleSet,
+    seed: u64,
+) -> SimulationStats {
+    (0..n_hands)
+        .into_par_iter()
+        .map(|hand| {
+            let mut rng = StdRng::seed_from_u64(hand_seed(seed, hand));
+            let mut cards = Card::all();
+            cards.shuffle(&mut rng);
+            let deal = [0, 1, 2, 3].map(|p| cards[p * 8..p * 8 + 8].to_vec());
+
+            let agents = [0, 1, 2, 3].map(|seat| (seat + hand) % 4);
+            let mut table = Match::with_rules(rule_set);
+            for agent in agents {
+                table
+                    .register_player(agent_factories[agent](rng.gen()))
+                    .expect("a new match has four free seats");
+            }
+            let result = table
+                .play_deal(deal)
+                .expect("a match with four agents can play a deal");
+            let mut stats = SimulationStats::default();
+            stats.add(agents, &result);
+            stats
+        })
+        .reduce(SimulationStats::default, SimulationStats::merge)
+}
+
+/// The `hand`th output of SplitMix64 started at `seed`, so that neighbouring seeds don't
+/// just shift the same sequence of deals by one.
+fn hand_seed(seed: u64, hand: usize) -> u64 {
+    let mut z = seed.wrapping_add((hand as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::HeuristicAgent;
+    use schafkopf_lib::schafkopf_env::agent::RandomAgent;
+
+    fn factories() -> [SeededFactory; 4] {
+        [
+            Box::new(|_| Box::new(HeuristicAgent::new())),
+            Box::new(|seed| Box::new(RandomAgent::with_seed(seed))),
+            Box::new(|seed| Box::new(RandomAgent::with_seed(seed))),
+            Box::new(|seed| Box::new(RandomAgent::with_seed(seed))),
+        ]
+    }
+
+    #[test]
+    fn test_simulation_is_deterministic_per_seed() {
+        let run = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| simulate(200, &factories(), RuleSet::default(), 7))
+        };
+        let stats = run(1);
+        assert_eq!(stats, run(4));
+        assert_ne!(stats, simulate(200, &factories(), RuleSet::default(), 8));
+
+        assert_eq!(stats.hands, 200);
+        assert_eq!(stats.earnings.iter().sum::<i64>(), 0);
+        let played = stats.contracts.values().map(|c| c.played).sum::<usize>();
+        assert_eq!(played, 200);
+        let frequencies = stats.contracts.keys().map(|c| stats.frequency(*c));
+        assert!((frequencies.sum::<f64>() - 1.0).abs() < 1e-9);
+        // the heuristic agent should beat three random ones
+        assert!(stats.mean_earnings(0) > stats.mean_earnings(1));
+    }
+
+    #[test]
+    fn test_neighbouring_seeds_deal_other_hands() {
+        let seeds = (0..1000)
+            .map(|hand| hand_seed(7, hand))
+            .collect::<Vec<u64>>();
+        for hand in 0..1000 {
+            assert!(!seeds.contains(&hand_seed(8, hand)));
+        }
+    }
+}
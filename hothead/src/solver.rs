@@ -0,0 +1,691 @@
+use schafkopf_lib::schafkopf_env::game_logic::{
+    is_trump, plain_rank, trump_rank, Card, Contract, Game, Value,
+};
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::time::Instant;
+
+/// Outcome of a perfect-information search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolverResult {
+    /// Card points the team ends the game with under optimal play by everyone.
+    pub points: u8,
+    /// Final card points of the team for every legal card of the player to move.
+    pub card_values: Vec<(Card, u8)>,
+}
+
+/// Double-dummy solver: plays out a game with all hands visible.
+///
+/// The solver is an alpha-beta search over the remaining cards in which the given team
+/// maximizes its card points and everyone else minimizes them. Positions at the start of
+/// a trick are cached in a transposition table and cards that are equivalent within a
+/// hand are only searched once.
+pub struct Solver {
+    table: HashMap<(u128, u32), Entry, BuildHasherDefault<KeyHasher>>,
+    /// Number of positions visited since the solver was created.
+    pub nodes: u64,
+    /// Searches still running at this point are abandoned, see `timed_out`.
+    pub deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+#[derive(Copy, Clone)]
+struct Entry {
+    lower: u8,
+    upper: u8,
+    best: u8,
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver {
+            table: HashMap::default(),
+            nodes: 0,
+            deadline: None,
+            timed_out: false,
+        }
+    }
+
+    /// Solves the game from its current position and values every legal card of the player to move.
+    pub fn solve(&mut self, game: &Game, team: [bool; 4]) -> SolverResult {
+        let (tables, position) = self.prepare(game, team, false);
+        self.solve_position(game, tables, position)
+    }
+
+    /// Solves a Ramsch for `player`: the player avoids card points while everybody else
+    /// tries to push them onto the player. Points are the ones the player ends up with.
+    pub fn solve_ramsch(&mut self, game: &Game, player: usize) -> SolverResult {
+        let mut team = [false; 4];
+        team[player] = true;
+        let (tables, position) = self.prepare(game, team, true);
+        self.solve_position(game, tables, position)
+    }
+
+    fn solve_position(
+        &mut self,
+        game: &Game,
+        tables: Tables,
+        mut position: Position,
+    ) -> SolverResult {
+        let won = won_points(game, &position.team);
+
+        let moves = position.legal_moves(&tables);
+        let mut card_values = Vec::new();
+        let mut points = 0;
+        for card in iter_bits(moves) {
+            let value = won
+                + self.exact(|solver, alpha, beta| {
+                    solver.play(&tables, &mut position, card, alpha, beta)
+                });
+            points = if card_values.is_empty() || position.maximizing() {
+                points.max(value)
+            } else {
+                points.min(value)
+            };
+            card_values.push((Card::from_index(card), value));
+        }
+        if card_values.is_empty() {
+            points = won;
+        }
+
+        SolverResult {
+            points,
+            card_values,
+        }
+    }
+
+    /// Returns only the card points the team ends the game with, which is cheaper than `solve`.
+    pub fn value(&mut self, game: &Game, team: [bool; 4]) -> u8 {
+        let (tables, mut position) = self.prepare(game, team, false);
+        won_points(game, &team)
+            + self.exact(|solver, alpha, beta| solver.search(&tables, &mut position, alpha, beta))
+    }
+
+    /// True if the last search ran past the deadline. Its results are meaningless then.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Finds the exact value of a search with a sequence of zero-window probes (MTD(f)),
+    /// which prune far more than a single search over the full window.
+    fn exact<F: FnMut(&mut Solver, i32, i32) -> i32>(&mut self, mut search: F) -> u8 {
+        let (mut lower, mut upper) = (0, 121);
+        let mut guess = 60;
+        while lower + 1 < upper && !self.timed_out {
+            let beta = guess.max(lower + 1);
+            let value = search(self, beta - 1, beta);
+            if value < beta {
+                upper = value + 1;
+            } else {
+                lower = value;
+            }
+            guess = value;
+        }
+        lower as u8
+    }
+
+    fn prepare(&mut self, game: &Game, team: [bool; 4], misere: bool) -> (Tables, Position) {
+        self.table.clear();
+        self.timed_out = false;
+        let tables = Tables::new(&game.contract);
+        let mut hands = [0; 4];
+        for (player, hand) in game.hands.iter().enumerate() {
+            hands[player] = hand.cards.iter().fold(0, |acc, c| acc | 1 << c.index());
+        }
+        let trick = game.current_trick();
+        let mut position = Position {
+            hands,
+            trick: [0; 4],
+            trick_len: trick.len(),
+            leader: trick.first().map_or(game.next_player, |c| c.1),
+            ran_away: game.ran_away(),
+            team,
+            misere,
+        };
+        for (i, played) in trick.iter().enumerate() {
+            position.trick[i] = played.0.index() as u8;
+        }
+        (tables, position)
+    }
+
+    /// Plays `card` for the player to move and searches the rest of the game.
+    /// Returns the points the team takes from the current trick on.
+    fn play(
+        &mut self,
+        tables: &Tables,
+        position: &mut Position,
+        card: usize,
+        alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let player = position.to_move();
+        let ran_away = position.ran_away;
+        if position.trick_len == 0
+            && tables.called_ace.is_some_and(|ace| {
+                position.hands[player] & 1 << ace != 0 && tables.called_suit & 1 << card != 0
+            })
+            && Some(card) != tables.called_ace
+        {
+            position.ran_away = true;
+        }
+        position.hands[player] &= !(1 << card);
+        position.trick[position.trick_len] = card as u8;
+        position.trick_len += 1;
+
+        let value = if position.trick_len == 4 {
+            let (winner, points) = tables.trick_result(&position.trick, position.leader);
+            let gained = if position.team[winner] { points } else { 0 };
+            let (trick, leader) = (position.trick, position.leader);
+            position.trick_len = 0;
+            position.leader = winner;
+            let value = gained + self.search(tables, position, alpha - gained, beta - gained);
+            position.trick = trick;
+            position.leader = leader;
+            position.trick_len = 4;
+            value
+        } else {
+            self.search(tables, position, alpha, beta)
+        };
+
+        position.trick_len -= 1;
+        position.hands[player] |= 1 << card;
+        position.ran_away = ran_away;
+        value
+    }
+
+    fn search(&mut self, tables: &Tables, position: &mut Position, alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return 0;
+        }
+        let at_trick_start = position.trick_len == 0;
+        // the team can't take more than what is left, nor less than nothing
+        let remaining = tables.mask_points(position.live());
+        if remaining <= alpha || (at_trick_start && remaining == 0) {
+            return remaining;
+        }
+        if beta <= 0 {
+            return 0;
+        }
+
+        if at_trick_start && position.hands.iter().all(|h| h.count_ones() == 1) {
+            // last trick: every card is forced
+            let mut trick = [0; 4];
+            for (i, card) in trick.iter_mut().enumerate() {
+                *card = position.hands[(position.leader + i) % 4].trailing_zeros() as u8;
+            }
+            let (winner, points) = tables.trick_result(&trick, position.leader);
+            return if position.team[winner] { points } else { 0 };
+        }
+
+        let (mut alpha, mut beta) = (alpha, beta);
+        let key = if at_trick_start {
+            position.key(tables)
+        } else {
+            (0, 0)
+        };
+        let mut hint = None;
+        if at_trick_start {
+            if let Some(entry) = self.table.get(&key) {
+                let (lower, upper) = (entry.lower as i32, entry.upper as i32);
+                if lower == upper || lower >= beta {
+                    return lower;
+                }
+                if upper <= alpha {
+                    return upper;
+                }
+                alpha = alpha.max(lower);
+                beta = beta.min(upper);
+                hint = Some(entry.best as usize);
+            }
+        }
+        let (alpha_start, beta_start) = (alpha, beta);
+
+        let maximizing = position.maximizing();
+        let mut best_value = if maximizing { -1 } else { 121 };
+        let mut best_card = 0;
+        for card in self.ordered_moves(tables, position, hint) {
+            let value = self.play(tables, position, card, alpha, beta);
+            if maximizing {
+                if value > best_value {
+                    best_value = value;
+                    best_card = card;
+                }
+                alpha = alpha.max(value);
+            } else {
+                if value < best_value {
+                    best_value = value;
+                    best_card = card;
+                }
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if at_trick_start && !self.timed_out {
+            let entry = self.table.entry(key).or_insert(Entry {
+                lower: 0,
+                upper: 120,
+                best: best_card as u8,
+            });
+            if best_value <= alpha_start {
+                entry.upper = entry.upper.min(best_value as u8);
+            } else if best_value >= beta_start {
+                entry.lower = entry.lower.max(best_value as u8);
+            } else {
+                entry.lower = best_value as u8;
+                entry.upper = best_value as u8;
+            }
+            entry.best = best_card as u8;
+        }
+        best_value
+    }
+
+    /// Legal moves without equivalent cards, most promising first.
+    fn ordered_moves(
+        &self,
+        tables: &Tables,
+        position: &Position,
+        hint: Option<usize>,
+    ) -> Vec<usize> {
+        let legal = position.legal_moves(tables);
+        let live = position.live();
+
+        let mut moves = Vec::with_capacity(8);
+        for class in tables.classes.iter() {
+            let mut previous: Option<usize> = None;
+            for &card in class.iter() {
+                if legal & 1 << card != 0 {
+                    // neighbouring cards worth the same points are interchangeable
+                    let equivalent =
+                        previous.is_some_and(|p| tables.points[p] == tables.points[card]);
+                    if !equivalent {
+                        moves.push(card);
+                    }
+                    previous = Some(card);
+                } else if live & 1 << card != 0 {
+                    previous = None;
+                }
+            }
+        }
+
+        let player = position.to_move();
+        let winning = if position.trick_len == 0 {
+            None
+        } else {
+            Some(tables.trick_winner(&position.trick[..position.trick_len], position.leader))
+        };
+        moves.sort_by_cached_key(|&card| {
+            let score = match winning {
+                // lead with the strongest cards
+                None => tables.strength[card] as i32,
+                Some((winner, best)) => {
+                    let beats_best = tables.beats(card, best);
+                    if position.team[winner] == position.team[player] && !beats_best {
+                        // partner is winning: schmier
+                        tables.points[card] as i32 * 4
+                    } else if beats_best && position.team[winner] != position.team[player] {
+                        // take the trick as cheaply as possible
+                        100 - tables.strength[card] as i32 + tables.points[best] as i32
+                    } else {
+                        -(tables.points[card] as i32)
+                    }
+                }
+            };
+            let hinted = if Some(card) == hint { 1000 } else { 0 };
+            -(score + hinted)
+        });
+        moves
+    }
+}
+
+fn won_points(game: &Game, team: &[bool; 4]) -> u8 {
+    game.get_points()
+        .iter()
+        .enumerate()
+        .filter(|(player, _)| team[*player])
+        .map(|(_, points)| points)
+        .sum()
+}
+
+fn iter_bits(mask: u32) -> impl Iterator<Item = usize> {
+    let mut mask = mask;
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
+
+/// Multiplicative hasher for the transposition table keys, which are already well mixed bitmasks.
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write_u64(i as u64 ^ (i >> 64) as u64 ^ (i >> 96) as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+/// Contract dependent lookup tables, indexed by `Card::index`.
+struct Tables {
+    trump: u32,
+    class_masks: [u32; 5],
+    class_of: [u8; 32],
+    strength: [u8; 32],
+    points: [u8; 32],
+    /// Cards of every class ordered from weakest to strongest.
+    classes: Vec<Vec<usize>>,
+    called_ace: Option<usize>,
+    called_suit: u32,
+}
+
+impl Tables {
+    fn new(contract: &Contract) -> Tables {
+        let mut tables = Tables {
+            trump: 0,
+            class_masks: [0; 5],
+            class_of: [0; 32],
+            strength: [0; 32],
+            points: [0; 32],
+            classes: vec![Vec::new(); 5],
+            called_ace: None,
+            called_suit: 0,
+        };
+        for card in Card::all() {
+            let index = card.index();
+            let class = if is_trump(&card, contract) {
+                tables.trump |= 1 << index;
+                4
+            } else {
+                card.suit as usize
+            };
+            tables.class_masks[class] |= 1 << index;
+            tables.class_of[index] = class as u8;
+            tables.strength[index] = match trump_rank(&card, contract) {
+                Some(rank) => 16 + rank,
+                None => plain_rank(&card),
+            };
+            tables.points[index] = card.points();
+            tables.classes[class].push(index);
+        }
+        for class in tables.classes.iter_mut() {
+            class.sort_by_key(|c| tables.strength[*c]);
+        }
+        if let Contract::Call(suit) = contract {
+            tables.called_ace = Some(
+                Card {
+                    suit: *suit,
+                    value: Value::Ace,
+                }
+                .index(),
+            );
+            tables.called_suit = tables.class_masks[*suit as usize];
+        }
+        tables
+    }
+
+    fn mask_points(&self, mask: u32) -> i32 {
+        iter_bits(mask).map(|c| self.points[c] as i32).sum()
+    }
+
+    fn beats(&self, card: usize, best: usize) -> bool {
+        let card_trump = self.trump & 1 << card != 0;
+        let best_trump = self.trump & 1 << best != 0;
+        match (card_trump, best_trump) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => {
+                self.class_of[card] == self.class_of[best]
+                    && self.strength[card] > self.strength[best]
+            }
+        }
+    }
+
+    /// Player and card currently winning a (possibly incomplete) trick.
+    fn trick_winner(&self, trick: &[u8], leader: usize) -> (usize, usize) {
+        let mut winner = (leader, trick[0] as usize);
+        for (i, card) in trick.iter().enumerate().skip(1) {
+            if self.beats(*card as usize, winner.1) {
+                winner = ((leader + i) % 4, *card as usize);
+            }
+        }
+        winner
+    }
+
+    fn trick_result(&self, trick: &[u8; 4], leader: usize) -> (usize, i32) {
+        let points = trick.iter().map(|c| self.points[*c as usize] as i32).sum();
+        (self.trick_winner(trick, leader).0, points)
+    }
+}
+
+struct Position {
+    hands: [u32; 4],
+    trick: [u8; 4],
+    trick_len: usize,
+    leader: usize,
+    ran_away: bool,
+    team: [bool; 4],
+    /// The team minimizes its points and everybody else maximizes them.
+    misere: bool,
+}
+
+impl Position {
+    fn to_move(&self) -> usize {
+        (self.leader + self.trick_len) % 4
+    }
+
+    fn maximizing(&self) -> bool {
+        self.team[self.to_move()] != self.misere
+    }
+
+    /// Cards still in a hand or in the current trick.
+    fn live(&self) -> u32 {
+        self.hands.iter().fold(0, |acc, h| acc | h) | self.trick_mask()
+    }
+
+    fn trick_mask(&self) -> u32 {
+        self.trick[..self.trick_len]
+            .iter()
+            .fold(0, |acc, c| acc | 1 << c)
+    }
+
+    /// Key of a position at the start of a trick. Only the order of the remaining cards
+    /// within their class matters, so positions that differ only in which of two
+    /// neighbouring cards is already gone share an entry.
+    fn key(&self, tables: &Tables) -> (u128, u32) {
+        let (mut low, mut high) = (0u64, 0u64);
+        for class in tables.classes.iter() {
+            for &card in class.iter() {
+                if let Some(owner) = self.hands.iter().position(|h| h & 1 << card != 0) {
+                    let code = (owner as u64) << 4 | tables.points[card] as u64;
+                    low = (low.rotate_left(7) ^ code).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                    high = (high.rotate_left(11) ^ code).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+                }
+            }
+            low = low.rotate_left(3) ^ 0xff;
+            high = high.rotate_left(5) ^ 0xff;
+        }
+        let state = self.leader as u32 | (self.ran_away as u32) << 2;
+        ((low as u128) << 64 | high as u128, state)
+    }
+
+    /// Mirrors `Game::get_legal_actions` on bitmasks.
+    fn legal_moves(&self, tables: &Tables) -> u32 {
+        let hand = self.hands[self.to_move()];
+        let ace = tables.called_ace.map_or(0, |ace| 1 << ace);
+        let ace_bound = hand & ace != 0 && !self.ran_away;
+
+        if self.trick_len == 0 {
+            let called = hand & tables.called_suit;
+            if ace_bound && called.count_ones() < 4 {
+                return hand & !(called & !ace);
+            }
+            return hand;
+        }
+
+        let lead_class = tables.class_of[self.trick[0] as usize] as usize;
+        let lead_mask = tables.class_masks[lead_class];
+        if ace_bound && lead_mask == tables.called_suit {
+            return ace;
+        }
+        if hand & lead_mask != 0 {
+            return hand & lead_mask;
+        }
+        if ace_bound && hand != ace {
+            return hand & !ace;
+        }
+        hand
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::seeded_game;
+    use schafkopf_lib::schafkopf_env::game_logic::Suit;
+
+    fn deal(contract: Contract, seed: u64) -> Game {
+        let mut game = seeded_game(0, seed);
+        game.contract = contract;
+        game
+    }
+
+    /// Plain minimax over `Game` itself, used as reference for the solver.
+    /// With `misere` the team minimizes its points instead.
+    fn minimax(game: &Game, team: &[bool; 4], misere: bool) -> u8 {
+        if game.is_over() {
+            let points = game.get_points();
+            return (0..4).filter(|p| team[*p]).map(|p| points[p]).sum();
+        }
+        let player = game.next_player;
+        let values = game
+            .get_legal_actions(&game.hands[player])
+            .into_iter()
+            .map(|card| {
+                let mut next = game.clone();
+                next.play_card(*card);
+                minimax(&next, team, misere)
+            });
+        if team[player] != misere {
+            values.max().unwrap()
+        } else {
+            values.min().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_match_game() {
+        for contract in [
+            Contract::Call(Suit::Acorns),
+            Contract::Solo(Suit::Bells),
+            Contract::Wenz,
+        ] {
+            for seed in 0..20 {
+                let mut game = deal(contract, seed);
+                let tables = Tables::new(&game.contract);
+                while !game.is_over() {
+                    let (_, position) = Solver::new().prepare(&game, [false; 4], false);
+                    let mut expected = game
+                        .get_legal_actions(&game.hands[game.next_player])
+                        .into_iter()
+                        .map(|c| c.index())
+                        .collect::<Vec<usize>>();
+                    expected.sort();
+                    let legal = iter_bits(position.legal_moves(&tables)).collect::<Vec<usize>>();
+                    assert_eq!(legal, expected);
+                    game.play_card(Card::from_index(legal[legal.len() / 2]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_minimax_in_endgame() {
+        for contract in [
+            Contract::Call(Suit::Leaves),
+            Contract::Solo(Suit::Hearts),
+            Contract::Wenz,
+        ] {
+            for seed in 0..10 {
+                let mut game = deal(contract, seed);
+                // play randomly into the last three tricks
+                while game.played.len() < 21 {
+                    let card = *game.get_legal_actions(&game.hands[game.next_player])[0];
+                    game.play_card(card);
+                }
+                let team = game.declaring_team();
+                let result = Solver::new().solve(&game, team);
+                assert_eq!(result.points, minimax(&game, &team, false));
+                for (card, value) in result.card_values {
+                    let mut next = game.clone();
+                    next.play_card(card);
+                    assert_eq!(value, minimax(&next, &team, false));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ramsch_matches_misere_minimax() {
+        for seed in 0..10 {
+            let mut game = deal(Contract::Ramsch, seed);
+            while game.played.len() < 21 {
+                let card = *game.get_legal_actions(&game.hands[game.next_player])[0];
+                game.play_card(card);
+            }
+            let player = game.next_player;
+            let mut team = [false; 4];
+            team[player] = true;
+            let result = Solver::new().solve_ramsch(&game, player);
+            assert_eq!(result.points, minimax(&game, &team, true));
+            for (card, value) in result.card_values {
+                let mut next = game.clone();
+                next.play_card(card);
+                assert_eq!(value, minimax(&next, &team, true));
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_deal_points_split() {
+        let game = deal(Contract::Solo(Suit::Acorns), 1);
+        let team = game.declaring_team();
+        let opponents = team.map(|t| !t);
+        let mut solver = Solver::new();
+        let points = solver.value(&game, team);
+        assert_eq!(points as u32 + solver.value(&game, opponents) as u32, 120);
+    }
+}
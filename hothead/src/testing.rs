@@ -0,0 +1,14 @@
+//! Helpers shared by the tests.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use schafkopf_lib::schafkopf_env::game_logic::{Card, Game};
+
+/// A fixed deal, so a failing test fails the same way on every run.
+pub fn seeded_game(forehand: usize, seed: u64) -> Game {
+    let mut cards = Card::all();
+    cards.shuffle(&mut StdRng::seed_from_u64(seed));
+    let hands = [0, 1, 2, 3].map(|p| cards[p * 8..p * 8 + 8].to_vec());
+    Game::from_hands(forehand, hands)
+}
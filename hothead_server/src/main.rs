@@ -0,0 +1,37 @@
+mod server;
+mod table;
+
+use hothead::bot::{Bot, BotConfig, Level};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use server::Lobby;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{env, io, process};
+use table::BotFactory;
+use tokio::net::TcpListener;
+
+/// Hosts tables for WebSocket clients, e.g. `hothead_server 0.0.0.0:8080 expert`.
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let level = args.next().unwrap_or_else(|| "intermediate".to_string());
+    let level = match level.parse::<Level>() {
+        Ok(level) => level,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+    // a table waits for its bots, so keep them quick
+    let config = BotConfig::new(level).with_time_limit(Duration::from_millis(500));
+    let bots: BotFactory =
+        Arc::new(move || -> Box<dyn Agent> { Box::new(Bot::new(config.clone())) });
+
+    let lobby = Arc::new(Lobby::new(bots));
+    lobby.open_table("Stammtisch");
+    let listener = TcpListener::bind(&address).await?;
+    println!("Listening on ws://{}", listener.local_addr()?);
+    server::serve(listener, lobby).await;
+    Ok(())
+}
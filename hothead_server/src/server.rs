@@ -0,0 +1,447 @@
+//! The WebSocket side of the server.
+//!
+//! Clients send one JSON object per text message, with a `"type"`:
+//!
+//! - `list`: answered with `{"type":"tables","tables":[{"table":0,"name":..,"players":[..]}]}`
+//! - `create`: `{"type":"create","name":"Stammtisch"}`, answered with `{"type":"created","table":0}`.
+//!   The table closes once nobody has been connected to it for the grace period.
+//! - `join`: `{"type":"join","table":0,"name":"Sepp"}` takes over a seat from a bot and
+//!   leaves the table the client sat at before, answered with
+//!   `{"type":"joined","table":0,"seat":1,"token":".."}`
+//! - `reconnect`: `{"type":"reconnect","table":0,"token":".."}` takes the seat back after
+//!   the connection was lost, a bot plays for it in the meantime. After the grace period
+//!   a joining player may take the seat. A player who doesn't decide within the turn
+//!   timeout loses the seat the same way.
+//! - `leave`: gives the seat back to a bot
+//! - `deal`: starts the next hand
+//! - `respond`: answers a decision like a bot of the `protocol` does, e.g.
+//!   `{"type":"respond","card":"Acorns Ace"}`
+//!
+//! Seated players get the decisions in the `protocol` format, their own view of the hand as
+//! `{"type":"state","observation":{..}}` and what happens at the table as
+//! `{"type":"event","event":"card","player":2,"card":"Bells Ten"}`. Anything that goes
+//! wrong is answered with `{"type":"error","message":".."}`.
+
+use crate::table::{error, send, spawn, BotFactory, ClientId, Command, Outbox, TableHandle};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value as Json};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::unbounded_channel;
+use tokio_tungstenite::tungstenite::Message;
+
+/// The tables of the server.
+pub struct Lobby {
+    /// Most tables open at a time, clients can't create more.
+    pub max_tables: usize,
+    /// How long a seat is kept for a player who lost the connection, and a table
+    /// created by a client for nobody.
+    pub grace: Duration,
+    /// How long a player may take for a decision before a bot takes over the seat.
+    pub turn_timeout: Duration,
+    tables: Mutex<Vec<TableHandle>>,
+    bots: BotFactory,
+    next_table: AtomicUsize,
+    next_client: AtomicUsize,
+}
+
+impl Lobby {
+    pub fn new(bots: BotFactory) -> Lobby {
+        Lobby {
+            max_tables: 32,
+            grace: Duration::from_secs(60),
+            turn_timeout: Duration::from_secs(60),
+            tables: Mutex::new(Vec::new()),
+            bots,
+            next_table: AtomicUsize::new(0),
+            next_client: AtomicUsize::new(0),
+        }
+    }
+
+    /// Opens a table with four bots that stays open, returns its id.
+    pub fn open_table(&self, name: &str) -> usize {
+        self.add_table(name, false)
+    }
+
+    /// Opens a table for a client, unless there are too many already.
+    pub fn create_table(&self, name: &str) -> Result<usize, String> {
+        if self.open_tables().len() >= self.max_tables {
+            return Err("There are too many tables".to_string());
+        }
+        Ok(self.add_table(name, true))
+    }
+
+    fn add_table(&self, name: &str, closes: bool) -> usize {
+        let id = self.next_table.fetch_add(1, Ordering::Relaxed);
+        let table = spawn(
+            id,
+            name,
+            self.bots.clone(),
+            self.grace,
+            self.turn_timeout,
+            closes,
+        );
+        self.tables.lock().unwrap().push(table);
+        id
+    }
+
+    /// Forgets the tables whose threads have ended, returns the others.
+    fn open_tables(&self) -> Vec<TableHandle> {
+        let mut tables = self.tables.lock().unwrap();
+        tables.retain(|t| !t.info.lock().unwrap().closed);
+        tables.clone()
+    }
+
+    fn table(&self, id: usize) -> Option<TableHandle> {
+        self.open_tables()
+            .into_iter()
+            .find(|t| t.info.lock().unwrap().id == id)
+    }
+
+    fn list(&self) -> Json {
+        let tables = self
+            .open_tables()
+            .iter()
+            .map(|t| t.info.lock().unwrap().to_json())
+            .collect::<Vec<Json>>();
+        json!({"type": "tables", "tables": tables})
+    }
+}
+
+/// Accepts connections until the listener fails.
+pub async fn serve(listener: TcpListener, lobby: Arc<Lobby>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(connection(stream, lobby.clone()));
+    }
+}
+
+/// One client, from the handshake until the connection closes.
+async fn connection(stream: TcpStream, lobby: Arc<Lobby>) {
+    let socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+    let (mut sink, mut stream) = socket.split();
+    let (outbox, mut messages) = unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = messages.recv().await {
+            if sink.send(Message::Text(message)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut client = Client {
+        id: lobby.next_client.fetch_add(1, Ordering::Relaxed),
+        outbox,
+        table: None,
+    };
+    while let Some(Ok(message)) = stream.next().await {
+        match message {
+            Message::Text(text) => client.handle(&lobby, &text),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    if let Some(table) = client.table.take() {
+        let _ = table
+            .commands
+            .send(Command::Disconnect { client: client.id });
+    }
+    writer.abort();
+}
+
+/// A connection and the table it sits at.
+struct Client {
+    id: ClientId,
+    outbox: Outbox,
+    table: Option<TableHandle>,
+}
+
+impl Client {
+    fn handle(&mut self, lobby: &Lobby, text: &str) {
+        let message: Json = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(e) => return send(&self.outbox, error(&format!("Invalid JSON: {}", e))),
+        };
+        let command = match message["type"].as_str().unwrap_or_default() {
+            "list" => return send(&self.outbox, lobby.list()),
+            "create" => {
+                let message = match lobby.create_table(message["name"].as_str().unwrap_or("Table"))
+                {
+                    Ok(id) => json!({"type": "created", "table": id}),
+                    Err(message) => error(&message),
+                };
+                return send(&self.outbox, message);
+            }
+            kind @ ("join" | "reconnect") => {
+                // a client sits at one table at a time, also after a failed join
+                if let Some(table) = self.table.take() {
+                    self.send_to(&table, Command::Leave { client: self.id });
+                }
+                let table = match message["table"]
+                    .as_u64()
+                    .and_then(|id| lobby.table(id as usize))
+                {
+                    Some(table) => table,
+                    None => return send(&self.outbox, error("No such table")),
+                };
+                let outbox = self.outbox.clone();
+                let command = if kind == "join" {
+                    let name = message["name"].as_str().unwrap_or("Player").to_string();
+                    Command::Join {
+                        client: self.id,
+                        outbox,
+                        name,
+                    }
+                } else {
+                    let token = message["token"].as_str().unwrap_or_default().to_string();
+                    Command::Reconnect {
+                        client: self.id,
+                        outbox,
+                        token,
+                    }
+                };
+                self.table = Some(table.clone());
+                return self.send_to(&table, command);
+            }
+            "leave" => match self.table.take() {
+                Some(table) => return self.send_to(&table, Command::Leave { client: self.id }),
+                None => return,
+            },
+            "deal" => Command::Deal { client: self.id },
+            "respond" => Command::Respond {
+                client: self.id,
+                line: text.to_string(),
+            },
+            other => return send(&self.outbox, error(&format!("Unknown type {:?}", other))),
+        };
+        match self.table.clone() {
+            Some(table) => self.send_to(&table, command),
+            None => send(&self.outbox, error("You aren't seated")),
+        }
+    }
+
+    fn send_to(&self, table: &TableHandle, command: Command) {
+        if table.commands.send(command).is_err() {
+            send(&self.outbox, error("The table is closed"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schafkopf_lib::schafkopf_env::agent::{Agent, RandomAgent};
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    fn lobby() -> Lobby {
+        let bots: BotFactory = Arc::new(|| -> Box<dyn Agent> { Box::new(RandomAgent::new()) });
+        Lobby::new(bots)
+    }
+
+    async fn listen(lobby: Lobby) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, Arc::new(lobby)));
+        address
+    }
+
+    async fn start() -> String {
+        let lobby = lobby();
+        lobby.open_table("Test");
+        listen(lobby).await
+    }
+
+    async fn join(address: &str) -> (Socket, Json) {
+        let (mut socket, _) = connect_async(address).await.unwrap();
+        send_json(
+            &mut socket,
+            json!({"type": "join", "table": 0, "name": "Sepp"}),
+        )
+        .await;
+        let joined = expect(&mut socket, "joined").await;
+        (socket, joined)
+    }
+
+    async fn send_json(socket: &mut Socket, message: Json) {
+        socket
+            .send(Message::Text(message.to_string()))
+            .await
+            .unwrap();
+    }
+
+    /// Skips messages until one of the type arrives.
+    async fn expect(socket: &mut Socket, kind: &str) -> Json {
+        while let Some(message) = socket.next().await {
+            if let Message::Text(text) = message.unwrap() {
+                let message: Json = serde_json::from_str(&text).unwrap();
+                if message["type"] == kind {
+                    return message;
+                }
+                assert_ne!(message["type"], "error", "{}", message);
+            }
+        }
+        panic!("connection closed before a {} message", kind);
+    }
+
+    #[tokio::test]
+    async fn test_plays_a_hand_against_bots() {
+        let (mut socket, _) = connect_async(start().await).await.unwrap();
+        send_json(
+            &mut socket,
+            json!({"type": "join", "table": 0, "name": "Sepp"}),
+        )
+        .await;
+        let joined = expect(&mut socket, "joined").await;
+        send_json(&mut socket, json!({"type": "list"})).await;
+        let tables = expect(&mut socket, "tables").await;
+        assert_eq!(
+            tables["tables"][0]["players"][joined["seat"].as_u64().unwrap() as usize],
+            "Sepp"
+        );
+
+        send_json(&mut socket, json!({"type": "deal"})).await;
+        loop {
+            let text = match socket.next().await.unwrap().unwrap() {
+                Message::Text(text) => text,
+                _ => continue,
+            };
+            let message: Json = serde_json::from_str(&text).unwrap();
+            match message["type"].as_str().unwrap() {
+                "intent" => {
+                    assert!(message["observation"]["hand"].is_array());
+                    send_json(&mut socket, json!({"type": "respond", "intent": false})).await;
+                }
+                "bid" => {
+                    let bid = message["valid_bids"][0].clone();
+                    send_json(&mut socket, json!({"type": "respond", "bid": bid})).await;
+                }
+                "play" => {
+                    let card = message["legal_plays"][0].clone();
+                    send_json(&mut socket, json!({"type": "respond", "card": card})).await;
+                }
+                "event" if message["event"] == "deal" => {
+                    assert!(message.get("hands").is_none());
+                }
+                "event" if message["event"] == "settled" => break,
+                "error" => panic!("{}", message),
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_takes_the_seat_back() {
+        let address = start().await;
+        let (mut socket, _) = connect_async(&address).await.unwrap();
+        send_json(
+            &mut socket,
+            json!({"type": "join", "table": 0, "name": "Sepp"}),
+        )
+        .await;
+        let joined = expect(&mut socket, "joined").await;
+        socket.close(None).await.unwrap();
+
+        let (mut other, _) = connect_async(&address).await.unwrap();
+        send_json(
+            &mut other,
+            json!({"type": "reconnect", "table": 0, "token": "nope"}),
+        )
+        .await;
+        let message = expect(&mut other, "error").await;
+        assert_eq!(message["message"], "Unknown token");
+
+        let (mut socket, _) = connect_async(&address).await.unwrap();
+        let token = joined["token"].clone();
+        send_json(
+            &mut socket,
+            json!({"type": "reconnect", "table": 0, "token": token}),
+        )
+        .await;
+        let rejoined = expect(&mut socket, "joined").await;
+        assert_eq!(rejoined["seat"], joined["seat"]);
+    }
+
+    #[tokio::test]
+    async fn test_join_takes_seats_abandoned_for_the_grace_period() {
+        let mut lobby = lobby();
+        lobby.grace = Duration::from_millis(100);
+        lobby.open_table("Test");
+        let address = listen(lobby).await;
+        let mut sockets = Vec::new();
+        for _ in 0..4 {
+            sockets.push(join(&address).await);
+        }
+        let (mut gone, joined) = sockets.pop().unwrap();
+        gone.close(None).await.unwrap();
+
+        let (mut late, _) = connect_async(&address).await.unwrap();
+        let message = json!({"type": "join", "table": 0, "name": "Vroni"});
+        send_json(&mut late, message.clone()).await;
+        assert_eq!(
+            expect(&mut late, "error").await["message"],
+            "The table is full"
+        );
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        send_json(&mut late, message).await;
+        assert_eq!(expect(&mut late, "joined").await["seat"], joined["seat"]);
+        let (mut back, _) = connect_async(&address).await.unwrap();
+        let token = joined["token"].clone();
+        send_json(
+            &mut back,
+            json!({"type": "reconnect", "table": 0, "token": token}),
+        )
+        .await;
+        assert_eq!(expect(&mut back, "error").await["message"], "Unknown token");
+    }
+
+    #[tokio::test]
+    async fn test_tables_are_capped_and_close_when_empty() {
+        let mut lobby = lobby();
+        lobby.max_tables = 1;
+        lobby.grace = Duration::from_millis(100);
+        let (mut socket, _) = connect_async(listen(lobby).await).await.unwrap();
+        let create = json!({"type": "create", "name": "Stammtisch"});
+        send_json(&mut socket, create.clone()).await;
+        assert_eq!(expect(&mut socket, "created").await["table"], 0);
+        send_json(&mut socket, create.clone()).await;
+        let message = expect(&mut socket, "error").await;
+        assert_eq!(message["message"], "There are too many tables");
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        send_json(&mut socket, json!({"type": "list"})).await;
+        let tables = expect(&mut socket, "tables").await;
+        assert_eq!(tables["tables"], json!([]));
+        send_json(&mut socket, create).await;
+        assert_eq!(expect(&mut socket, "created").await["table"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_player_loses_the_seat_to_a_bot() {
+        let mut lobby = lobby();
+        lobby.turn_timeout = Duration::from_millis(100);
+        lobby.open_table("Test");
+        let address = listen(lobby).await;
+        let (mut socket, joined) = join(&address).await;
+        send_json(&mut socket, json!({"type": "deal"})).await;
+        let message = expect(&mut socket, "error").await;
+        assert_eq!(
+            message["message"],
+            "You took too long, a bot plays until you reconnect"
+        );
+
+        let token = joined["token"].clone();
+        send_json(
+            &mut socket,
+            json!({"type": "reconnect", "table": 0, "token": token}),
+        )
+        .await;
+        assert_eq!(expect(&mut socket, "joined").await["seat"], joined["seat"]);
+    }
+}
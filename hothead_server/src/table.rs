@@ -0,0 +1,452 @@
+use rand::Rng;
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::match_logic::{Match, MatchError, Progress, Request, Seat};
+use schafkopf_lib::schafkopf_env::observer::MatchEvent;
+use schafkopf_lib::schafkopf_env::protocol::{
+    card_name, contract_name, observation, parse_response, request_message,
+};
+use serde_json::{json, Value as Json};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+pub type ClientId = usize;
+/// Messages to a client, sent on by its connection.
+pub type Outbox = UnboundedSender<String>;
+/// Creates the bots that fill the empty seats.
+pub type BotFactory = Arc<dyn Fn() -> Box<dyn Agent> + Send + Sync>;
+
+/// What clients ask a table to do.
+pub enum Command {
+    Join {
+        client: ClientId,
+        outbox: Outbox,
+        name: String,
+    },
+    /// Takes back the seat the token was handed out for.
+    Reconnect {
+        client: ClientId,
+        outbox: Outbox,
+        token: String,
+    },
+    /// Gives up the seat for good.
+    Leave {
+        client: ClientId,
+    },
+    /// The connection is gone, the seat is kept for a reconnect.
+    Disconnect {
+        client: ClientId,
+    },
+    Deal {
+        client: ClientId,
+    },
+    /// A decision, in the reply format of the bot protocol.
+    Respond {
+        client: ClientId,
+        line: String,
+    },
+}
+
+/// What the lobby shows of a table.
+#[derive(Clone, Debug, Default)]
+pub struct TableInfo {
+    pub id: usize,
+    pub name: String,
+    /// The humans in the seats, bots play the rest.
+    pub players: [Option<String>; 4],
+    /// Set once the table's thread has ended.
+    pub closed: bool,
+}
+
+impl TableInfo {
+    pub fn to_json(&self) -> Json {
+        json!({"table": self.id, "name": self.name, "players": self.players})
+    }
+}
+
+/// The lobby's end of a table running on its own thread.
+#[derive(Clone)]
+pub struct TableHandle {
+    pub commands: Sender<Command>,
+    pub info: Arc<Mutex<TableInfo>>,
+}
+
+struct Occupant {
+    name: String,
+    token: String,
+    /// The connection, if the player is connected.
+    client: Option<(ClientId, Outbox)>,
+    /// When the connection was lost.
+    disconnected: Option<Instant>,
+}
+
+/// A match in which humans can take over seats from bots and give them back.
+///
+/// `Match` isn't `Send`, so every table lives on a thread of its own and is only
+/// talked to through `Command`s. Everything a client gets to see goes through its outbox:
+/// public events for everybody, the hand and decisions only for the player whose they are.
+struct Table {
+    table: Match,
+    events: Receiver<MatchEvent>,
+    seats: [Option<Occupant>; 4],
+    bots: BotFactory,
+    info: Arc<Mutex<TableInfo>>,
+    /// How long a seat is kept for a player who lost the connection.
+    grace: Duration,
+    /// How long a player may take for a decision.
+    turn_timeout: Duration,
+    /// The decision a connected player has to make, and since when.
+    turn: Option<(Request, Instant)>,
+}
+
+/// Starts the table's thread. With `closes` the thread ends once nobody has been
+/// connected for the grace period, otherwise it runs as long as the lobby keeps the handle.
+/// A player who doesn't decide within `turn_timeout` loses the seat to a bot, as if the
+/// connection was gone.
+pub fn spawn(
+    id: usize,
+    name: &str,
+    bots: BotFactory,
+    grace: Duration,
+    turn_timeout: Duration,
+    closes: bool,
+) -> TableHandle {
+    let info = Arc::new(Mutex::new(TableInfo {
+        id,
+        name: name.to_string(),
+        ..TableInfo::default()
+    }));
+    let (commands, receiver) = channel();
+    let handle = TableHandle {
+        commands,
+        info: info.clone(),
+    };
+    thread::spawn(move || {
+        let mut table = Match::new();
+        for _ in 0..4 {
+            table
+                .register_player(bots())
+                .expect("a new match has four seats");
+        }
+        let (sender, events) = channel();
+        table.add_observer(Box::new(sender));
+        let mut table = Table {
+            table,
+            events,
+            seats: [None, None, None, None],
+            bots,
+            info,
+            grace,
+            turn_timeout,
+            turn: None,
+        };
+        loop {
+            let command = match table.time_left() {
+                Some(left) => match receiver.recv_timeout(left) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => {
+                        table.time_out();
+                        table.drive();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                },
+                None if closes && table.is_empty() => receiver.recv_timeout(grace).ok(),
+                None => receiver.recv().ok(),
+            };
+            match command {
+                Some(command) => {
+                    table.handle(command);
+                    table.drive();
+                }
+                None => break,
+            }
+        }
+        table.info.lock().unwrap().closed = true;
+    });
+    handle
+}
+
+impl Table {
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Join {
+                client,
+                outbox,
+                name,
+            } => {
+                let seat = match self.seats.iter().position(|s| self.is_free(s)) {
+                    Some(seat) => seat,
+                    None => return send(&outbox, error("The table is full")),
+                };
+                let token = format!("{:016x}", rand::thread_rng().gen::<u64>());
+                self.seats[seat] = Some(Occupant {
+                    name,
+                    token,
+                    client: None,
+                    disconnected: None,
+                });
+                self.connect(seat, client, outbox);
+            }
+            Command::Reconnect {
+                client,
+                outbox,
+                token,
+            } => {
+                let seat = self
+                    .seats
+                    .iter()
+                    .position(|s| s.as_ref().is_some_and(|occupant| occupant.token == token));
+                match seat {
+                    Some(seat) => self.connect(seat, client, outbox),
+                    None => send(&outbox, error("Unknown token")),
+                }
+            }
+            Command::Leave { client } => {
+                if let Some(seat) = self.seat_of(client) {
+                    self.seats[seat] = None;
+                    self.seat_bot(seat);
+                }
+            }
+            Command::Disconnect { client } => {
+                if let Some(seat) = self.seat_of(client) {
+                    self.disconnect(seat);
+                }
+            }
+            Command::Deal { client } => {
+                if let Err(e) = self.table.start_hand() {
+                    self.send_to_client(client, error(&e.to_string()));
+                }
+            }
+            Command::Respond { client, line } => {
+                if let Err(message) = self.respond(client, &line) {
+                    self.send_to_client(client, error(&message));
+                }
+            }
+        }
+        self.update_info();
+    }
+
+    fn respond(&mut self, client: ClientId, line: &str) -> Result<(), String> {
+        let seat = self.seat_of(client).ok_or("You aren't seated")?;
+        let request = self.table.request().ok_or("Nothing to decide")?;
+        if request.player() != seat {
+            return Err("It isn't your turn".to_string());
+        }
+        let response = parse_response(line, &request).map_err(|e| e.to_string())?;
+        self.table.respond(response).map_err(|e| e.to_string())
+    }
+
+    /// Lets the bots play until a human has to decide, and tells everybody what happened.
+    fn drive(&mut self) {
+        let request = match self.table.poll() {
+            Ok(Progress::Waiting(request)) => Some(request),
+            Ok(Progress::HandOver(_)) | Err(MatchError::NoHand) => None,
+            Err(e) => {
+                self.broadcast(error(&e.to_string()));
+                None
+            }
+        };
+        self.publish_events();
+        for seat in 0..4 {
+            self.send_state(seat, request.as_ref());
+        }
+        // the clock keeps running while the same decision is pending
+        self.turn = match (request, self.turn.take()) {
+            (Some(request), Some((pending, since))) if request == pending => Some((pending, since)),
+            (Some(request), _) if self.outbox(request.player()).is_some() => {
+                Some((request, Instant::now()))
+            }
+            _ => None,
+        };
+    }
+
+    /// How long the player to decide has left, if it's a connected player's turn.
+    fn time_left(&self) -> Option<Duration> {
+        self.turn
+            .as_ref()
+            .map(|(_, since)| self.turn_timeout.saturating_sub(since.elapsed()))
+    }
+
+    /// Lets a bot take over from the player who took too long.
+    fn time_out(&mut self) {
+        if let Some((request, _)) = self.turn.take() {
+            let seat = request.player();
+            if let Some(outbox) = self.outbox(seat) {
+                send(
+                    outbox,
+                    error("You took too long, a bot plays until you reconnect"),
+                );
+            }
+            self.disconnect(seat);
+            self.update_info();
+        }
+    }
+
+    /// Seats the connection as the occupant of `seat`.
+    fn connect(&mut self, seat: usize, client: ClientId, outbox: Outbox) {
+        let occupant = self.seats[seat].as_mut().expect("the seat is taken");
+        occupant.disconnected = None;
+        if let Some((_, old)) = occupant.client.replace((client, outbox.clone())) {
+            send(&old, error("Connected from somewhere else"));
+        }
+        let joined = json!({
+            "type": "joined",
+            "table": self.info.lock().unwrap().id,
+            "seat": seat,
+            "token": occupant.token,
+        });
+        send(&outbox, joined);
+        self.table
+            .replace_seat(Seat::Remote, seat)
+            .expect("the match has four seats");
+    }
+
+    /// Keeps the seat for a reconnect and lets a bot play for it meanwhile.
+    fn disconnect(&mut self, seat: usize) {
+        if let Some(occupant) = self.seats[seat].as_mut() {
+            occupant.client = None;
+            occupant.disconnected = Some(Instant::now());
+        }
+        self.seat_bot(seat);
+    }
+
+    /// Lets a bot play for the seat.
+    fn seat_bot(&mut self, seat: usize) {
+        self.table
+            .replace_player((self.bots)(), seat)
+            .expect("the match has four seats");
+    }
+
+    /// Whether a joining player may take the seat: it's empty, or its occupant
+    /// didn't come back within the grace period.
+    fn is_free(&self, seat: &Option<Occupant>) -> bool {
+        match seat {
+            Some(occupant) => occupant
+                .disconnected
+                .is_some_and(|since| since.elapsed() >= self.grace),
+            None => true,
+        }
+    }
+
+    /// Whether nobody is connected to the table.
+    fn is_empty(&self) -> bool {
+        self.seats
+            .iter()
+            .flatten()
+            .all(|occupant| occupant.client.is_none())
+    }
+
+    fn seat_of(&self, client: ClientId) -> Option<usize> {
+        self.seats.iter().position(|s| {
+            s.as_ref()
+                .and_then(|o| o.client.as_ref())
+                .is_some_and(|(id, _)| *id == client)
+        })
+    }
+
+    fn update_info(&self) {
+        let mut info = self.info.lock().unwrap();
+        for (seat, occupant) in self.seats.iter().enumerate() {
+            info.players[seat] = occupant.as_ref().map(|o| o.name.clone());
+        }
+    }
+
+    fn outbox(&self, seat: usize) -> Option<&Outbox> {
+        self.seats[seat]
+            .as_ref()
+            .and_then(|o| o.client.as_ref())
+            .map(|(_, outbox)| outbox)
+    }
+
+    fn send_to_client(&self, client: ClientId, message: Json) {
+        if let Some(outbox) = self.seat_of(client).and_then(|seat| self.outbox(seat)) {
+            send(outbox, message);
+        }
+    }
+
+    fn broadcast(&self, message: Json) {
+        for seat in 0..4 {
+            if let Some(outbox) = self.outbox(seat) {
+                send(outbox, message.clone());
+            }
+        }
+    }
+
+    /// Sends the player their own view of the hand, and the request if it's their turn.
+    fn send_state(&self, seat: usize, request: Option<&Request>) {
+        let (outbox, state) = match (self.outbox(seat), self.table.player_state(seat)) {
+            (Some(outbox), Some(state)) => (outbox, state),
+            _ => return,
+        };
+        let auction = self.table.auction().filter(|a| !a.is_finished());
+        match request.filter(|r| r.player() == seat) {
+            Some(request) => send(outbox, request_message(request, &state, auction)),
+            None => send(
+                outbox,
+                json!({"type": "state", "observation": observation(&state, auction)}),
+            ),
+        }
+    }
+
+    /// Passes the match events on, leaving out the dealt hands.
+    fn publish_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            let message = match event {
+                MatchEvent::Deal {
+                    hand_nr, forehand, ..
+                } => json!({"event": "deal", "hand": hand_nr, "forehand": forehand}),
+                MatchEvent::Intent { player, intent } => {
+                    json!({"event": "intent", "player": player, "intent": intent})
+                }
+                MatchEvent::Bid { player, contract } => {
+                    json!({"event": "bid", "player": player, "bid": contract_name(&contract)})
+                }
+                MatchEvent::ContractDecided { contract, declarer } => json!({
+                    "event": "contract",
+                    "contract": contract_name(&contract),
+                    "declarer": declarer,
+                }),
+                MatchEvent::CardPlayed { player, card } => {
+                    json!({"event": "card", "player": player, "card": card_name(&card)})
+                }
+                MatchEvent::Fault(fault) => json!({"event": "fault", "player": fault.player}),
+                MatchEvent::TrickWon {
+                    winner,
+                    points,
+                    trick,
+                } => json!({
+                    "event": "trick",
+                    "winner": winner,
+                    "points": points,
+                    "cards": trick.iter().map(|p| card_name(&p.0)).collect::<Vec<String>>(),
+                }),
+                MatchEvent::HandSettled { result, score } => json!({
+                    "event": "settled",
+                    "contract": contract_name(&result.contract),
+                    "declarer": result.declarer,
+                    "points": result.points,
+                    "earnings": result.earnings,
+                    "score": score,
+                }),
+                MatchEvent::MatchFinished { hands, score } => {
+                    json!({"event": "finished", "hands": hands, "score": score})
+                }
+            };
+            let mut message = message;
+            message["type"] = json!("event");
+            self.broadcast(message);
+        }
+    }
+}
+
+pub fn error(message: &str) -> Json {
+    json!({"type": "error", "message": message})
+}
+
+/// Sends the message unless the connection is already gone.
+pub fn send(outbox: &Outbox, message: Json) {
+    let _ = outbox.send(message.to_string());
+}
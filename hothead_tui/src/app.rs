@@ -0,0 +1,123 @@
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::console::TableView;
+use schafkopf_lib::schafkopf_env::match_logic::{MatchError, Request, Response};
+
+/// Keys the app reacts to, independent of the terminal library.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Quit,
+}
+
+/// The table as the human player sees it, and where they are on the screen.
+pub struct App {
+    pub view: TableView,
+    /// Index of the highlighted option of the request.
+    pub selected: usize,
+    /// Lines the log is scrolled up from its end.
+    pub log_scroll: usize,
+    pub quit: bool,
+}
+
+impl App {
+    /// Seats the human first and the bots after them, and deals the first hand.
+    pub fn new(bots: [Box<dyn Agent>; 3]) -> Result<App, MatchError> {
+        Ok(App {
+            view: TableView::new(bots)?,
+            selected: 0,
+            log_scroll: 0,
+            quit: false,
+        })
+    }
+
+    /// What the human can choose from right now, as text.
+    pub fn options(&self) -> Vec<String> {
+        match &self.view.request {
+            Some(Request::Intent { .. }) => vec!["Pass".to_string(), "I'd play".to_string()],
+            Some(Request::Bid { valid_bids, .. }) => {
+                valid_bids.iter().map(|c| c.to_string()).collect()
+            }
+            Some(Request::Play { .. }) => {
+                self.view.playable().iter().map(|c| c.to_string()).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) -> Result<(), MatchError> {
+        let options = self.options().len();
+        match key {
+            Key::Quit => {
+                self.view.stop();
+                self.quit = true;
+            }
+            Key::Left if options > 0 => self.selected = (self.selected + options - 1) % options,
+            Key::Right if options > 0 => self.selected = (self.selected + 1) % options,
+            Key::Up => self.log_scroll = (self.log_scroll + 1).min(self.view.log.len()),
+            Key::Down => self.log_scroll = self.log_scroll.saturating_sub(1),
+            Key::Enter => {
+                match &self.view.request {
+                    Some(request) => {
+                        let response = match request {
+                            Request::Intent { .. } => Response::Intent(self.selected == 1),
+                            Request::Bid { valid_bids, .. } => {
+                                Response::Bid(valid_bids[self.selected])
+                            }
+                            Request::Play { .. } => {
+                                Response::Play(self.view.playable()[self.selected])
+                            }
+                        };
+                        self.view.respond(response)?;
+                    }
+                    None => self.view.next_hand()?,
+                }
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schafkopf_lib::schafkopf_env::agent::RandomAgent;
+
+    fn app() -> App {
+        let bots: [Box<dyn Agent>; 3] =
+            [1, 2, 3].map(|seed| -> Box<dyn Agent> { Box::new(RandomAgent::with_seed(seed)) });
+        App::new(bots).unwrap()
+    }
+
+    #[test]
+    fn test_keys_play_a_hand() {
+        let mut app = app();
+        assert!(matches!(app.view.request, Some(Request::Intent { .. })));
+        app.handle_key(Key::Right).unwrap();
+        app.handle_key(Key::Right).unwrap();
+        assert_eq!(app.selected, 0);
+        app.handle_key(Key::Left).unwrap();
+        assert_eq!(app.selected, 1);
+
+        // always the first option, so every answer is valid
+        app.handle_key(Key::Left).unwrap();
+        while app.view.request.is_some() {
+            if let Some(Request::Play { legal_plays, .. }) = &app.view.request {
+                assert_eq!(app.view.playable().len(), legal_plays.len());
+            }
+            app.handle_key(Key::Enter).unwrap();
+        }
+        assert_eq!(app.view.results.len(), 1);
+        assert!(app.view.hand().is_empty());
+
+        app.handle_key(Key::Enter).unwrap();
+        assert_eq!(app.view.hand().len(), 8);
+        app.handle_key(Key::Quit).unwrap();
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,53 @@
+mod app;
+mod ui;
+
+use app::{App, Key};
+use hothead::bot::{Bot, BotConfig, Level};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use std::time::Duration;
+use std::{env, io, process};
+
+/// Full-screen table against three hothead bots, e.g. `hothead_tui expert`.
+fn main() -> io::Result<()> {
+    let level = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "intermediate".to_string());
+    let level = match level.parse::<Level>() {
+        Ok(level) => level,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+    // bots think while the screen waits, so keep them quick
+    let config = BotConfig::new(level).with_time_limit(Duration::from_millis(500));
+    let bots = [0, 1, 2].map(|_| -> Box<dyn Agent> { Box::new(Bot::new(config.clone())) });
+    let mut app = App::new(bots).map_err(io::Error::other)?;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key.code,
+            _ => continue,
+        };
+        let key = match key {
+            KeyCode::Left | KeyCode::Char('h') => Key::Left,
+            KeyCode::Right | KeyCode::Char('l') => Key::Right,
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Enter | KeyCode::Char(' ') => Key::Enter,
+            KeyCode::Char('q') | KeyCode::Esc => Key::Quit,
+            _ => continue,
+        };
+        app.handle_key(key).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
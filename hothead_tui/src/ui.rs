@@ -0,0 +1,232 @@
+use crate::app::App;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, Wrap};
+use ratatui::Frame;
+use schafkopf_lib::schafkopf_env::game_logic::PlayedCard;
+use schafkopf_lib::schafkopf_env::match_logic::Request;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [main, help] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [left, right] =
+        Layout::horizontal([Constraint::Min(40), Constraint::Length(40)]).areas(main);
+    let [table, hand] = Layout::vertical([Constraint::Min(9), Constraint::Length(6)]).areas(left);
+    let [scores, log] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Min(5)]).areas(right);
+
+    draw_table(frame, app, table);
+    draw_hand(frame, app, hand);
+    draw_scores(frame, app, scores);
+    draw_log(frame, app, log);
+    frame.render_widget(
+        Paragraph::new("←/→ choose  Enter confirm  ↑/↓ scroll log  q quit").dark_gray(),
+        help,
+    );
+}
+
+/// The other players around the trick, the human at the bottom.
+fn draw_table(frame: &mut Frame, app: &App, area: Rect) {
+    let view = &app.view;
+    let title = match view.contract {
+        Some((contract, declarer)) => format!(" {} by {} ", contract, view.name(declarer)),
+        None => " Auction ".to_string(),
+    };
+    let block = Block::bordered().title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // seats in playing order: left, across, right of the human
+    let seat = |offset: usize| (view.seat + offset) % 4;
+    let card = |player: usize| {
+        let trick = if view.trick.is_empty() {
+            view.last_trick.map(|(_, t)| t.to_vec()).unwrap_or_default()
+        } else {
+            view.trick.clone()
+        };
+        trick
+            .iter()
+            .find(|PlayedCard(_, p)| *p == player)
+            .map(|PlayedCard(c, _)| c.to_string())
+            .unwrap_or_else(|| "·".to_string())
+    };
+    let label = |player: usize| format!("{} ({:+})", view.name(player), view.score[player]);
+
+    let [top, middle, bottom] = Layout::vertical([Constraint::Ratio(1, 3); 3]).areas(inner);
+    let [west, center, east] = Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(middle);
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(label(seat(2))), Line::from(card(seat(2)))]).centered(),
+        top,
+    );
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(label(seat(1))), Line::from(card(seat(1)))]),
+        west,
+    );
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(label(seat(3))), Line::from(card(seat(3)))]).right_aligned(),
+        east,
+    );
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(card(view.seat)),
+            Line::from(label(view.seat)),
+        ])
+        .centered(),
+        bottom,
+    );
+    if let Some((winner, _)) = view.last_trick.filter(|_| view.trick.len() == 4) {
+        frame.render_widget(
+            Paragraph::new(format!("→ {}", view.name(winner)))
+                .centered()
+                .yellow(),
+            center,
+        );
+    }
+}
+
+/// The hand with the legal cards highlighted, and what there is to decide.
+fn draw_hand(frame: &mut Frame, app: &App, area: Rect) {
+    let view = &app.view;
+    let playable = view.playable();
+    let selected = playable.get(app.selected);
+    let cards = view
+        .hand()
+        .into_iter()
+        .flat_map(|card| {
+            let style = if Some(&card) == selected {
+                Style::new().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+            } else if playable.contains(&card) {
+                Style::new().fg(Color::White).add_modifier(Modifier::BOLD)
+            } else if matches!(view.request, Some(Request::Play { .. })) {
+                Style::new().fg(Color::DarkGray)
+            } else {
+                Style::new()
+            };
+            vec![Span::styled(format!(" {} ", card), style), Span::raw(" ")]
+        })
+        .collect::<Vec<Span>>();
+
+    let prompt = match &view.request {
+        Some(Request::Intent { .. }) => options(app, "Play? "),
+        Some(Request::Bid { .. }) => options(app, "Bid: "),
+        Some(Request::Play { .. }) => Line::from("Your card:"),
+        None => Line::from("Hand over, press Enter for the next one."),
+    };
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(cards), Line::default(), prompt])
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Your hand ")),
+        area,
+    );
+}
+
+/// The options of the request in a row, the selected one highlighted.
+fn options<'a>(app: &App, prompt: &'a str) -> Line<'a> {
+    let mut spans = vec![Span::raw(prompt)];
+    for (i, option) in app.options().into_iter().enumerate() {
+        let style = if i == app.selected {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new()
+        };
+        spans.push(Span::styled(format!(" {} ", option), style));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+/// One row per hand with everybody's earnings, the totals in the title.
+fn draw_scores(frame: &mut Frame, app: &App, area: Rect) {
+    let view = &app.view;
+    let header = Row::new(
+        ["#", "Game"]
+            .into_iter()
+            .map(String::from)
+            .chain((0..4).map(|p| {
+                if p == view.seat {
+                    "You".to_string()
+                } else {
+                    format!("P{}", p)
+                }
+            })),
+    )
+    .bold();
+    let rows = view.results.iter().enumerate().rev().map(|(i, result)| {
+        Row::new(
+            [(i + 1).to_string(), result.contract.to_string()]
+                .into_iter()
+                .chain(result.earnings.iter().map(|e| format!("{:+}", e))),
+        )
+    });
+    let widths = [
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(5),
+        Constraint::Length(5),
+        Constraint::Length(5),
+        Constraint::Length(5),
+    ];
+    let totals = view
+        .score
+        .iter()
+        .map(|s| format!("{:+}", s))
+        .collect::<Vec<String>>()
+        .join(" ");
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(format!(" Score {} ", totals))),
+        area,
+    );
+}
+
+fn draw_log(frame: &mut Frame, app: &App, area: Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    let end = app.view.log.len().saturating_sub(app.log_scroll);
+    let start = end.saturating_sub(height);
+    let lines = app.view.log[start..end]
+        .iter()
+        .map(|l| Line::from(l.as_str()))
+        .collect::<Vec<Line>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use schafkopf_lib::schafkopf_env::agent::{Agent, RandomAgent};
+
+    #[test]
+    fn test_draws_every_panel() {
+        let bots: [Box<dyn Agent>; 3] =
+            [1, 2, 3].map(|seed| -> Box<dyn Agent> { Box::new(RandomAgent::with_seed(seed)) });
+        let app = App::new(bots).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect::<String>();
+        for text in [
+            "Auction",
+            "Your hand",
+            "Score",
+            "Log",
+            "Player 2",
+            "Pass",
+            "q quit",
+        ] {
+            assert!(screen.contains(text), "{} is missing", text);
+        }
+    }
+}
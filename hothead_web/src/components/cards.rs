@@ -8,6 +8,12 @@ pub struct CardCompProps {
     pub card: Card,
     #[prop_or(0)]
     pub index: usize,
+    /// Whether the card may be played, the others are greyed out.
+    #[prop_or(true)]
+    pub playable: bool,
+    /// Called with the card and the pointer position when the card is let go after dragging.
+    #[prop_or_default]
+    pub ondrop: Callback<(Card, i32, i32)>,
 }
 
 pub enum Msg {
@@ -17,7 +23,10 @@ pub enum Msg {
 
 #[function_component]
 pub fn CardComp(props: &CardCompProps) -> Html {
-    let classes = vec!["p-card"];
+    let mut classes = vec!["p-card"];
+    if !props.playable {
+        classes.push("unplayable");
+    }
     let dragging_handle = use_state(|| false);
     let card_ref = use_node_ref();
     let x = use_state(|| 0);
@@ -40,8 +49,13 @@ pub fn CardComp(props: &CardCompProps) -> Html {
     let onmouseup = {
         let dragging_handle = dragging_handle.clone();
         let node_ref = card_ref.clone();
+        let ondrop = props.ondrop.clone();
+        let card = props.card;
 
         Callback::from(move |e: MouseEvent| {
+            if *dragging_handle {
+                ondrop.emit((card, e.client_x(), e.client_y()));
+            }
             dragging_handle.set(false);
             if let Some(card_elem) = node_ref.cast::<HtmlDivElement>() {
                 card_elem.style().remove_property("top").unwrap();
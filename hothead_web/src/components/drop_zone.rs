@@ -1,11 +1,34 @@
+use schafkopf_lib::schafkopf_env::game_logic::PlayedCard;
 use yew::prelude::*;
 
 #[derive(PartialEq, Properties)]
-pub struct DropZoneProps {}
+pub struct DropZoneProps {
+    /// Lets the playing field tell whether a card was dropped onto the zone.
+    #[prop_or_default]
+    pub zone_ref: NodeRef,
+    /// The cards of the trick, with the names of the players who played them.
+    #[prop_or_default]
+    pub trick: Vec<(PlayedCard, String)>,
+    /// Shown below the trick, e.g. who took it.
+    #[prop_or_default]
+    pub caption: Option<String>,
+}
 
 #[function_component]
 pub fn DropZone(props: &DropZoneProps) -> Html {
     html! {
-        <div class="drop-zone"></div>
+        <div ref={ props.zone_ref.clone() } class="drop-zone">
+            <div class="trick">
+                { for props.trick.iter().map(|(PlayedCard(card, _), name)| html! {
+                    <div class="trick-card">
+                        <div class="p-card">{ format!("{}", card) }</div>
+                        <div>{ name }</div>
+                    </div>
+                }) }
+            </div>
+            if let Some(caption) = &props.caption {
+                <div class="caption">{ caption }</div>
+            }
+        </div>
     }
 }
@@ -5,13 +5,21 @@ use yew::prelude::*;
 #[derive(PartialEq, Properties)]
 pub struct HandCompProps {
     pub cards: Vec<Card>,
+    /// The cards that may be played, all of them if `None`.
+    #[prop_or_default]
+    pub playable: Option<Vec<Card>>,
+    #[prop_or_default]
+    pub ondrop: Callback<(Card, i32, i32)>,
 }
 
 #[function_component]
 pub fn HandComp(props: &HandCompProps) -> Html {
     html! {
         <div class="hand">
-            { for props.cards.iter().enumerate().map(|(idx, card)| html! { <CardComp card={ card.clone() } index={ idx as usize }/> }) }
+            { for props.cards.iter().enumerate().map(|(idx, card)| {
+                let playable = props.playable.as_ref().map_or(true, |p| p.contains(card));
+                html! { <CardComp key={ card.to_string() } card={ card.clone() } index={ idx as usize } { playable } ondrop={ props.ondrop.clone() }/> }
+            }) }
         </div>
     }
 }
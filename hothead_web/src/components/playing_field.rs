@@ -1,6 +1,12 @@
 use crate::components::drop_zone::DropZone;
 use crate::components::hand::HandComp;
-use schafkopf_lib::schafkopf_env::game_logic::{Card, Suit, Value};
+use gloo::console::log;
+use hothead::bot::{Bot, BotConfig, Level};
+use schafkopf_lib::schafkopf_env::agent::Agent;
+use schafkopf_lib::schafkopf_env::console::TableView;
+use schafkopf_lib::schafkopf_env::game_logic::Card;
+use schafkopf_lib::schafkopf_env::match_logic::{Request, Response};
+use web_sys::HtmlDivElement;
 use yew::prelude::*;
 
 #[derive(PartialEq, Properties)]
@@ -8,18 +14,146 @@ pub struct PlayingFieldProps {}
 
 #[function_component]
 pub fn PlayingField(props: &PlayingFieldProps) -> Html {
-    let card1 = Card {
-        suit: Suit::Acorns,
-        value: Value::Ace,
+    let table = use_mut_ref(|| {
+        // wasm32-unknown-unknown has no clock for `Instant`, which the searching levels
+        // use for their time limit; beginners only follow rules of thumb
+        let config = BotConfig::new(Level::Beginner);
+        let bots = [0, 1, 2].map(|_| -> Box<dyn Agent> { Box::new(Bot::new(config.clone())) });
+        TableView::new(bots).expect("a new table has four seats")
+    });
+    let update = use_force_update();
+    let zone_ref = use_node_ref();
+
+    let respond = {
+        let table = table.clone();
+        let update = update.clone();
+        Callback::from(move |response: Response| {
+            if let Err(e) = table.borrow_mut().respond(response) {
+                log!(e.to_string());
+            }
+            update.force_update();
+        })
+    };
+    let next_hand = {
+        let table = table.clone();
+        let update = update.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Err(e) = table.borrow_mut().next_hand() {
+                log!(e.to_string());
+            }
+            update.force_update();
+        })
     };
-    let card2 = Card {
-        suit: Suit::Bells,
-        value: Value::Under,
+    // a card counts as played when it's let go above the drop zone
+    let ondrop = {
+        let table = table.clone();
+        let update = update.clone();
+        let zone_ref = zone_ref.clone();
+        Callback::from(move |(card, x, y): (Card, i32, i32)| {
+            let on_zone = zone_ref.cast::<HtmlDivElement>().map_or(false, |zone| {
+                let rect = zone.get_bounding_client_rect();
+                let (x, y) = (x as f64, y as f64);
+                x >= rect.left() && x <= rect.right() && y >= rect.top() && y <= rect.bottom()
+            });
+            if !on_zone {
+                return;
+            }
+            let mut table = table.borrow_mut();
+            if !table.playable().contains(&card) {
+                log!(format!("{} can't be played now", card));
+                return;
+            }
+            match table.respond(Response::Play(card)) {
+                Ok(()) => update.force_update(),
+                Err(e) => log!(e.to_string()),
+            }
+        })
     };
+
+    let table = table.borrow();
+    let seat = |offset: usize| (table.seat + offset) % 4;
+    let label = |player: usize| format!("{} ({:+})", table.name(player), table.score[player]);
+    let title = match table.contract {
+        Some((contract, declarer)) => format!("{} by {}", contract, table.name(declarer)),
+        None => "Auction".to_string(),
+    };
+    let trick = table
+        .trick
+        .iter()
+        .map(|played| (*played, table.name(played.1)))
+        .collect::<Vec<_>>();
+    let caption = table
+        .last_trick
+        .filter(|_| table.trick.len() == 4)
+        .map(|(winner, _)| format!("{} takes the trick", table.name(winner)));
+
+    let controls = match &table.request {
+        Some(Request::Intent { .. }) => html! {
+            <div class="controls">
+                <button onclick={ respond.reform(|_: MouseEvent| Response::Intent(false)) }>{ "Pass" }</button>
+                <button onclick={ respond.reform(|_: MouseEvent| Response::Intent(true)) }>{ "I'd play" }</button>
+            </div>
+        },
+        Some(Request::Bid { valid_bids, .. }) => html! {
+            <div class="controls">
+                { for valid_bids.iter().map(|&bid| html! {
+                    <button onclick={ respond.reform(move |_: MouseEvent| Response::Bid(bid)) }>{ bid.to_string() }</button>
+                }) }
+            </div>
+        },
+        Some(Request::Play { .. }) => html! {
+            <div class="controls">{ "Drag a card onto the table" }</div>
+        },
+        None => html! {},
+    };
+    let settlement = match table.settled() {
+        Some(result) => html! {
+            <div class="settlement">
+                <div class="heading">{ format!("{} by {}", result.contract, table.name(result.declarer)) }</div>
+                <table>
+                    <tbody>
+                    <tr>
+                        <th></th>
+                        { for (0..4).map(|p| html! { <th>{ table.name(p) }</th> }) }
+                    </tr>
+                    <tr>
+                        <td>{ "Points" }</td>
+                        { for result.points.iter().map(|p| html! { <td>{ p }</td> }) }
+                    </tr>
+                    <tr>
+                        <td>{ "Earnings" }</td>
+                        { for result.earnings.iter().map(|e| html! { <td>{ format!("{:+}", e) }</td> }) }
+                    </tr>
+                    <tr>
+                        <td>{ "Total" }</td>
+                        { for table.score.iter().map(|s| html! { <td>{ format!("{:+}", s) }</td> }) }
+                    </tr>
+                    </tbody>
+                </table>
+                <button onclick={ next_hand }>{ "Next hand" }</button>
+            </div>
+        },
+        None => html! {},
+    };
+    let playable = match table.request {
+        Some(Request::Play { .. }) => Some(table.playable()),
+        _ => None,
+    };
+
     html! {
         <div class="container">
-            <DropZone />
-            <HandComp cards={ vec![card1, card2] } />
+            <div class="heading title">{ title }</div>
+            <div class="seat across">{ label(seat(2)) }</div>
+            <div class="seat left">{ label(seat(1)) }</div>
+            <div class="seat right">{ label(seat(3)) }</div>
+            <div class="seat own">{ label(table.seat) }</div>
+            <DropZone { zone_ref } { trick } { caption } />
+            { controls }
+            { settlement }
+            <div class="log">
+                { for table.log.iter().rev().take(8).map(|line| html! { <div>{ line }</div> }) }
+            </div>
+            <HandComp cards={ table.hand() } { playable } { ondrop } />
         </div>
     }
 }
@@ -0,0 +1,22 @@
+#!/usr/bin/env python3
+"""A random bot talking the line-based JSON protocol, see schafkopf_env/protocol.rs.
+
+Play it from Rust with
+    SubprocessAgent::spawn(Command::new("python3").arg("examples/random_bot.py"))
+"""
+import json
+import random
+import sys
+
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "intent":
+        reply = {"intent": random.random() < 0.2}
+    elif message["type"] == "bid":
+        reply = {"bid": random.choice(message["valid_bids"])}
+    elif message["type"] == "play":
+        reply = {"card": random.choice(message["legal_plays"])}
+    else:
+        # game_over isn't answered
+        continue
+    print(json.dumps(reply), flush=True)
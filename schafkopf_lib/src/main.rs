@@ -1,7 +1,24 @@
-use schafkopf_lib::schafkopf_env::game_logic::Game;
+use schafkopf_lib::schafkopf_env::agent::RandomAgent;
+use schafkopf_lib::schafkopf_env::console::{play_hand, TableLog};
+use schafkopf_lib::schafkopf_env::match_logic::Match;
+use std::io;
 
-fn main() {
-    let game = Game::new(0);
-    let player = game.get_player_game_state(0);
-    println!("{}", player.hand);
+/// Plays hand after hand against three random agents until the input ends.
+fn main() -> io::Result<()> {
+    let mut table = Match::new();
+    let seat = table.register_remote().expect("the match is empty");
+    for _ in 0..3 {
+        table
+            .register_player(Box::new(RandomAgent::new()))
+            .expect("the match has free seats");
+    }
+    table.add_observer(Box::new(TableLog::new(io::stdout(), seat)));
+    println!("You are player {}. End the input (Ctrl-D) to quit.", seat);
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut output = io::stdout();
+    while play_hand(&mut table, seat, &mut input, &mut output)?.is_some() {}
+    table.stop();
+    Ok(())
 }
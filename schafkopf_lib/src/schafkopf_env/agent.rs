@@ -1,5 +1,5 @@
-use crate::schafkopf_env::game_logic::{Auction, Card, Contract, PlayerGameState};
-use rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng, Rng};
+use crate::schafkopf_env::game_logic::{Auction, Card, Contract, Game, PlayerGameState};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
 /// An agent is a player in the game.
 /// It can be a human player or a bot, but has to be able to make decisions regarding:
@@ -13,15 +13,26 @@ pub trait Agent {
     fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract;
     /// Returns the card the player wants to play.
     fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card;
+    /// Called after every game with the complete auction and the finished game,
+    /// so the agent can learn about the other players. Does nothing by default.
+    fn game_over(&mut self, _player_nr: usize, _auction: &Auction, _game: &Game) {}
 }
 
 pub struct RandomAgent {
-    rng: ThreadRng,
+    rng: StdRng,
 }
 
 impl RandomAgent {
     pub fn new() -> RandomAgent {
-        RandomAgent { rng: thread_rng() }
+        RandomAgent {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> RandomAgent {
+        RandomAgent {
+            rng: StdRng::seed_from_u64(seed),
+        }
     }
 }
 
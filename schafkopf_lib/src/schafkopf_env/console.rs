@@ -0,0 +1,456 @@
+use crate::schafkopf_env::agent::Agent;
+use crate::schafkopf_env::game_logic::{Card, Contract, PlayedCard};
+use crate::schafkopf_env::match_logic::{
+    HandResult, Match, MatchError, Progress, Request, Response,
+};
+use crate::schafkopf_env::observer::{MatchEvent, Observer};
+use std::cmp::Reverse;
+use std::fmt::Display;
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Plays one hand of `table` with a human in the remote seat `seat`, asking for every
+/// decision on `input` and `output`. Returns `None` if the input ends before the hand does.
+///
+/// The prompts only show the human's own hand, what the other players do is up to an
+/// observer such as `TableLog`.
+pub fn play_hand<R: BufRead, W: Write>(
+    table: &mut Match,
+    seat: usize,
+    input: &mut R,
+    output: &mut W,
+) -> io::Result<Option<HandResult>> {
+    table.start_hand().map_err(match_error)?;
+    loop {
+        let request = match table.poll().map_err(match_error)? {
+            Progress::HandOver(result) => return Ok(Some(result)),
+            Progress::Waiting(request) => request,
+        };
+        if request.player() != seat {
+            return Err(match_error(format!(
+                "seat {} is not played from the console",
+                request.player()
+            )));
+        }
+        if let Some(state) = table.player_state(seat) {
+            writeln!(output, "\nYour hand:\n{}", state.hand)?;
+        }
+        let response = match &request {
+            Request::Intent { .. } => {
+                match choose(input, output, "Do you want to play?", &["no", "yes"])? {
+                    Some(choice) => Response::Intent(choice == 1),
+                    None => return Ok(None),
+                }
+            }
+            Request::Bid { valid_bids, .. } => {
+                match choose(input, output, "Your bid:", valid_bids)? {
+                    Some(choice) => Response::Bid(valid_bids[choice]),
+                    None => return Ok(None),
+                }
+            }
+            Request::Play { legal_plays, .. } => {
+                match choose(input, output, "Your card:", legal_plays)? {
+                    Some(choice) => Response::Play(legal_plays[choice]),
+                    None => return Ok(None),
+                }
+            }
+        };
+        table.respond(response).map_err(match_error)?;
+    }
+}
+
+/// Lists the options and reads the number of one of them until a valid one is entered.
+/// Returns `None` once the input ends.
+fn choose<R: BufRead, W: Write, T: Display>(
+    input: &mut R,
+    output: &mut W,
+    prompt: &str,
+    options: &[T],
+) -> io::Result<Option<usize>> {
+    writeln!(output, "{}", prompt)?;
+    for (i, option) in options.iter().enumerate() {
+        writeln!(output, "  {}) {}", i + 1, option)?;
+    }
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim().parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => return Ok(Some(choice - 1)),
+            _ => writeln!(output, "Please enter a number from 1 to {}.", options.len())?,
+        }
+    }
+}
+
+fn match_error<E: Display>(error: E) -> io::Error {
+    io::Error::other(error.to_string())
+}
+
+/// Writes what happens at the table as one player sees it: the other hands stay hidden.
+pub struct TableLog<W: Write> {
+    pub output: W,
+    /// The seat of the player reading along.
+    pub seat: usize,
+}
+
+impl<W: Write> TableLog<W> {
+    pub fn new(output: W, seat: usize) -> TableLog<W> {
+        TableLog { output, seat }
+    }
+
+    fn name(&self, player: usize) -> String {
+        if player == self.seat {
+            "You".to_string()
+        } else {
+            format!("Player {}", player)
+        }
+    }
+
+    fn log(&mut self, event: &MatchEvent) -> io::Result<()> {
+        match event {
+            MatchEvent::Deal {
+                hand_nr, forehand, ..
+            } => writeln!(
+                self.output,
+                "\n=== Hand {}, {} to lead ===",
+                hand_nr + 1,
+                self.name(*forehand)
+            ),
+            MatchEvent::Intent { player, intent } => writeln!(
+                self.output,
+                "{}: {}",
+                self.name(*player),
+                if *intent { "I'd play" } else { "weiter" }
+            ),
+            MatchEvent::Bid { player, contract } => {
+                writeln!(self.output, "{}: {}", self.name(*player), contract)
+            }
+            MatchEvent::ContractDecided { contract, declarer } => match contract {
+                Contract::Ramsch => writeln!(self.output, "Nobody plays, it's a Ramsch."),
+                _ => writeln!(
+                    self.output,
+                    "{} {} a {}.",
+                    self.name(*declarer),
+                    if *declarer == self.seat {
+                        "play"
+                    } else {
+                        "plays"
+                    },
+                    contract
+                ),
+            },
+            MatchEvent::CardPlayed { player, card } => {
+                writeln!(self.output, "{} played {}", self.name(*player), card)
+            }
+            MatchEvent::Fault(fault) => writeln!(
+                self.output,
+                "{} failed to decide ({:?}).",
+                self.name(fault.player),
+                fault.kind
+            ),
+            MatchEvent::TrickWon {
+                winner,
+                points,
+                trick,
+            } => {
+                let cards = trick
+                    .iter()
+                    .map(|PlayedCard(card, _)| card.to_string())
+                    .collect::<Vec<String>>();
+                writeln!(
+                    self.output,
+                    "Trick [{}] goes to {} for {} points.",
+                    cards.join(", "),
+                    self.name(*winner),
+                    points
+                )
+            }
+            MatchEvent::HandSettled { result, score } => {
+                writeln!(self.output, "--- {} ---", result.contract)?;
+                for (player, total) in score.iter().enumerate() {
+                    writeln!(
+                        self.output,
+                        "{}: {} points, {:+} (total {:+})",
+                        self.name(player),
+                        result.points[player],
+                        result.earnings[player],
+                        total
+                    )?;
+                }
+                Ok(())
+            }
+            MatchEvent::MatchFinished { hands, score } => writeln!(
+                self.output,
+                "Match over after {} hands, you finished with {:+}.",
+                hands, score[self.seat]
+            ),
+        }
+    }
+}
+
+impl<W: Write> Observer for TableLog<W> {
+    fn notify(&mut self, event: &MatchEvent) {
+        // the log is a convenience, a closed output mustn't stop the match
+        let _ = self.log(event);
+    }
+}
+
+/// A match against bots as the human in one seat sees it, for clients that draw the table
+/// themselves.
+///
+/// The bots decide right away whenever it's their turn, so after every decision of the
+/// human the view waits for the next one, or for the next hand to be dealt.
+pub struct TableView {
+    pub seat: usize,
+    /// The decision the human has to make, if it's their turn.
+    pub request: Option<Request>,
+    pub contract: Option<(Contract, usize)>,
+    /// Cards of the trick being played.
+    pub trick: Vec<PlayedCard>,
+    /// The last complete trick and who took it.
+    pub last_trick: Option<(usize, [PlayedCard; 4])>,
+    /// One per settled hand.
+    pub results: Vec<HandResult>,
+    pub score: [i32; 4],
+    pub log: Vec<String>,
+    table: Match,
+    events: Receiver<MatchEvent>,
+}
+
+impl TableView {
+    /// Seats the human first and the bots after them, and deals the first hand.
+    pub fn new(bots: [Box<dyn Agent>; 3]) -> Result<TableView, MatchError> {
+        let mut table = Match::new();
+        let seat = table.register_remote()?;
+        for bot in bots {
+            table.register_player(bot)?;
+        }
+        let (sender, events) = channel();
+        table.add_observer(Box::new(sender));
+        let mut view = TableView {
+            seat,
+            request: None,
+            contract: None,
+            trick: Vec::new(),
+            last_trick: None,
+            results: Vec::new(),
+            score: [0; 4],
+            log: Vec::new(),
+            table,
+            events,
+        };
+        view.next_hand()?;
+        Ok(view)
+    }
+
+    /// The human's hand, sorted like `Hand` displays it.
+    pub fn hand(&self) -> Vec<Card> {
+        let mut cards = self
+            .table
+            .player_state(self.seat)
+            .map(|state| state.hand.cards.clone())
+            .unwrap_or_default();
+        cards.sort_by_key(|c| Reverse((c.suit, c.value)));
+        cards
+    }
+
+    /// The cards the human may play right now, in the order of the hand.
+    pub fn playable(&self) -> Vec<Card> {
+        match &self.request {
+            Some(Request::Play { legal_plays, .. }) => self
+                .hand()
+                .into_iter()
+                .filter(|c| legal_plays.contains(c))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// How the last hand ended, until the next one is dealt.
+    pub fn settled(&self) -> Option<&HandResult> {
+        self.results.last().filter(|_| self.request.is_none())
+    }
+
+    /// Carries out the human's decision and lets the bots play on.
+    pub fn respond(&mut self, response: Response) -> Result<(), MatchError> {
+        self.table.respond(response)?;
+        self.advance()
+    }
+
+    pub fn next_hand(&mut self) -> Result<(), MatchError> {
+        self.table.start_hand()?;
+        self.advance()
+    }
+
+    pub fn stop(&mut self) {
+        self.table.stop();
+    }
+
+    /// Lets the bots play until it's the human's turn or the hand is over.
+    fn advance(&mut self) -> Result<(), MatchError> {
+        let progress = self.table.poll()?;
+        while let Ok(event) = self.events.try_recv() {
+            let line = self.read(event);
+            self.log.push(line);
+        }
+        self.request = match progress {
+            Progress::Waiting(request) => Some(request),
+            Progress::HandOver(_) => None,
+        };
+        Ok(())
+    }
+
+    /// Updates the view with the event, returns its line for the log.
+    fn read(&mut self, event: MatchEvent) -> String {
+        match event {
+            MatchEvent::Deal {
+                hand_nr, forehand, ..
+            } => {
+                self.contract = None;
+                self.trick.clear();
+                self.last_trick = None;
+                format!("Hand {}, {} to lead", hand_nr + 1, self.name(forehand))
+            }
+            MatchEvent::Intent { player, intent } => format!(
+                "{}: {}",
+                self.name(player),
+                if intent { "I'd play" } else { "pass" }
+            ),
+            MatchEvent::Bid { player, contract } => {
+                format!("{}: {}", self.name(player), contract)
+            }
+            MatchEvent::ContractDecided { contract, declarer } => {
+                self.contract = Some((contract, declarer));
+                match contract {
+                    Contract::Ramsch => "Nobody plays, it's a Ramsch".to_string(),
+                    _ => format!("{} plays a {}", self.name(declarer), contract),
+                }
+            }
+            MatchEvent::CardPlayed { player, card } => {
+                if self.trick.len() == 4 {
+                    self.trick.clear();
+                }
+                self.trick.push(PlayedCard(card, player));
+                format!("{} played {}", self.name(player), card)
+            }
+            MatchEvent::Fault(fault) => {
+                format!("{} failed to decide", self.name(fault.player))
+            }
+            MatchEvent::TrickWon {
+                winner,
+                points,
+                trick,
+            } => {
+                self.last_trick = Some((winner, trick));
+                format!("{} takes the trick, {} points", self.name(winner), points)
+            }
+            MatchEvent::HandSettled { result, score } => {
+                self.score = score;
+                let line = format!(
+                    "{} settled, you {:+}",
+                    result.contract, result.earnings[self.seat]
+                );
+                self.results.push(result);
+                line
+            }
+            MatchEvent::MatchFinished { hands, .. } => {
+                format!("Match over after {} hands", hands)
+            }
+        }
+    }
+
+    pub fn name(&self, player: usize) -> String {
+        if player == self.seat {
+            "You".to_string()
+        } else {
+            format!("Player {}", player)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::agent::RandomAgent;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn table() -> (Match, Rc<RefCell<TableLog<Vec<u8>>>>) {
+        let mut table = Match::new();
+        table.register_remote().unwrap();
+        for seed in 1..4 {
+            table
+                .register_player(Box::new(RandomAgent::with_seed(seed)))
+                .unwrap();
+        }
+        let log = Rc::new(RefCell::new(TableLog::new(Vec::new(), 0)));
+        table.add_observer(Box::new(log.clone()));
+        (table, log)
+    }
+
+    #[test]
+    fn test_console_plays_a_hand() {
+        let (mut table, log) = table();
+        // typos first, then always the first option: no intent and the first legal card
+        let input = "x\n9\n".to_string() + &"1\n".repeat(40);
+        let mut output = Vec::new();
+        let result = play_hand(&mut table, 0, &mut input.as_bytes(), &mut output)
+            .unwrap()
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Do you want to play?"));
+        assert!(output.contains("Please enter a number from 1 to 2."));
+        assert!(output.contains("Your card:"));
+        let log = String::from_utf8(log.borrow().output.clone()).unwrap();
+        assert_eq!(log.matches("Trick [").count(), 8);
+        assert!(log.contains(&format!("You: {} points", result.points[0])));
+    }
+
+    #[test]
+    fn test_console_stops_at_end_of_input() {
+        let (mut table, _) = table();
+        let mut output = Vec::new();
+        let result = play_hand(&mut table, 0, &mut "1\n".as_bytes(), &mut output).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_view_follows_a_hand() {
+        let bots =
+            [1, 2, 3].map(|seed| -> Box<dyn Agent> { Box::new(RandomAgent::with_seed(seed)) });
+        let mut view = TableView::new(bots).unwrap();
+        assert_eq!(view.hand().len(), 8);
+        assert!(view.settled().is_none());
+
+        // always the first option, so every answer is valid
+        while let Some(request) = view.request.clone() {
+            let response = match request {
+                Request::Intent { .. } => Response::Intent(false),
+                Request::Bid { valid_bids, .. } => Response::Bid(valid_bids[0]),
+                Request::Play { legal_plays, .. } => {
+                    assert_eq!(view.playable().len(), legal_plays.len());
+                    Response::Play(view.playable()[0])
+                }
+            };
+            view.respond(response).unwrap();
+        }
+        assert!(view.hand().is_empty());
+        assert_eq!(view.trick.len(), 4);
+        assert!(view.contract.is_some() && view.last_trick.is_some());
+        let result = view.settled().unwrap();
+        assert_eq!(view.score, result.earnings);
+        assert!(view.log.last().unwrap().contains("settled"));
+
+        view.next_hand().unwrap();
+        assert_eq!(view.hand().len(), 8);
+        assert!(view.trick.is_empty() && view.last_trick.is_none());
+        assert!(view.settled().is_none());
+        assert_eq!(view.results.len(), 1);
+        assert!(view.log.contains(&"Hand 2, Player 1 to lead".to_string()));
+    }
+}
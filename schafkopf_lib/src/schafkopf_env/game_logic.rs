@@ -30,7 +30,7 @@ impl fmt::Display for Hand {
     }
 }
 
-#[derive(Copy, Clone, EnumIter, Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Copy, Clone, EnumIter, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Suit {
     Bells,
     Hearts,
@@ -49,7 +49,7 @@ impl fmt::Display for Suit {
     }
 }
 
-#[derive(Copy, Clone, EnumIter, Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Copy, Clone, EnumIter, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Value {
     Seven,
     Eight,
@@ -76,7 +76,7 @@ impl fmt::Display for Value {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Card {
     pub suit: Suit,
     pub value: Value,
@@ -93,6 +93,22 @@ impl Card {
         return cards;
     }
 
+    /// Position of the card in `Card::all()`, usable as a compact card id.
+    pub fn index(&self) -> usize {
+        self.suit as usize * 8 + self.value as usize
+    }
+
+    pub fn from_index(index: usize) -> Card {
+        Card {
+            suit: Suit::iter()
+                .nth(index / 8)
+                .expect("Card index out of range."),
+            value: Value::iter()
+                .nth(index % 8)
+                .expect("Card index out of range."),
+        }
+    }
+
     pub fn deck() -> Vec<Card> {
         let mut cards = Card::all();
         cards.shuffle(&mut rand::thread_rng());
@@ -117,7 +133,7 @@ impl fmt::Display for Card {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Contract {
     Call(Suit),
     Solo(Suit),
@@ -126,10 +142,67 @@ pub enum Contract {
     None,
 }
 
-#[derive(Debug)]
+impl fmt::Display for Contract {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Contract::Call(suit) => write!(f, "Call {}", suit),
+            Contract::Solo(suit) => write!(f, "Solo {}", suit),
+            Contract::Wenz => write!(f, "Wenz"),
+            Contract::Ramsch => write!(f, "Ramsch"),
+            Contract::None => write!(f, "Pass"),
+        }
+    }
+}
+
+/// Tariffs used to price and settle a game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RuleSet {
+    pub call_tariff: i32,
+    pub solo_tariff: i32,
+    pub ramsch_tariff: i32,
+    pub schneider_bonus: i32,
+    pub schwarz_bonus: i32,
+    pub laufende_bonus: i32,
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet {
+            call_tariff: 20,
+            solo_tariff: 50,
+            ramsch_tariff: 20,
+            schneider_bonus: 10,
+            schwarz_bonus: 10,
+            laufende_bonus: 10,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Base tariff of a contract, paid by (or to) each opponent.
+    pub fn tariff(&self, contract: &Contract) -> i32 {
+        match contract {
+            Contract::Call(_) => self.call_tariff,
+            Contract::Solo(_) | Contract::Wenz => self.solo_tariff,
+            Contract::Ramsch => self.ramsch_tariff,
+            Contract::None => 0,
+        }
+    }
+
+    /// Minimum number of consecutive top trumps that are paid as Laufende.
+    pub fn min_laufende(&self, contract: &Contract) -> usize {
+        match contract {
+            Contract::Wenz => 2,
+            _ => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Game {
     trick: u8,
     ran_away: bool,
+    pub forehand: usize,
     pub next_player: usize,
     pub declarer: usize,
     pub played: Vec<PlayedCard>,
@@ -137,8 +210,9 @@ pub struct Game {
     pub contract: Contract,
 }
 
-#[derive(Debug)]
-pub struct PlayedCard(Card, usize);
+/// A card together with the number of the player who played it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PlayedCard(pub Card, pub usize);
 
 impl Game {
     pub fn new(forehand_player: usize) -> Game {
@@ -146,6 +220,7 @@ impl Game {
         Game {
             trick: 0,
             ran_away: false,
+            forehand: forehand_player,
             next_player: forehand_player,
             declarer: 0,
             played: Vec::new(),
@@ -154,11 +229,30 @@ impl Game {
         }
     }
 
+    /// Creates a game from already dealt hands, e.g. to replay or analyse a known deal.
+    pub fn from_hands(forehand_player: usize, hands: [Vec<Card>; 4]) -> Game {
+        Game {
+            trick: 0,
+            ran_away: false,
+            forehand: forehand_player,
+            next_player: forehand_player,
+            declarer: 0,
+            played: Vec::new(),
+            hands: hands.map(|cards| Hand {
+                cards,
+                played: Vec::new(),
+            }),
+            contract: Contract::None,
+        }
+    }
+
     pub fn get_player_game_state(&self, player_nr: usize) -> PlayerGameState {
         PlayerGameState {
             hand: &self.hands[player_nr],
             contract: self.contract,
             player_nr,
+            forehand: self.forehand,
+            declarer: self.declarer,
             trick: &self.trick,
             played: &self.played,
         }
@@ -169,54 +263,24 @@ impl Game {
     }
 
     fn determine_trick_winner(&self, trick: u8) -> Option<usize> {
-        let trick_cards = self.played.iter().skip((trick * 4) as usize).take(4);
+        let trick_cards = self
+            .played
+            .iter()
+            .skip((trick * 4) as usize)
+            .take(4)
+            .collect::<Vec<&PlayedCard>>();
 
         if trick_cards.len() < 4 {
             return None;
         }
 
-        let trick_cards = trick_cards.collect::<Vec<&PlayedCard>>();
-        let leading_suit = &trick_cards[0].0.suit;
-
-        let winner = trick_cards.iter().max_by(|a, b| {
-            let (a_is_trump, b_is_trump) = (
-                is_trump(&a.0, &self.contract),
-                is_trump(&b.0, &self.contract),
-            );
-            let (a_is_lead_suit, b_is_lead_suit) =
-                (a.0.suit == *leading_suit, b.0.suit == *leading_suit);
-            if a_is_trump && !b_is_trump {
-                // trump wins over non-trump
-                return Ordering::Greater;
-            } else if !a_is_trump && b_is_trump {
-                // trump wins over non-trump
-                return Ordering::Less;
-            } else if a_is_trump && b_is_trump {
-                // Ober and Under win against numbered trumps
-                let mut cmp = a.0.value.cmp(&b.0.value);
-                if cmp == Ordering::Equal {
-                    cmp = a.0.suit.cmp(&b.0.suit);
-                }
-                return cmp;
-            // neither cards are trumps
-            } else if a_is_lead_suit && !b_is_lead_suit {
-                // lead suit wins over non-lead suit
-                return Ordering::Greater;
-            } else if !a_is_lead_suit && b_is_lead_suit {
-                // lead suit wins over non-lead suit
-                return Ordering::Less;
-            } else if a_is_lead_suit && b_is_lead_suit {
-                // higher value wins if both are lead suit
-                return a.0.value.cmp(&b.0.value);
+        let mut winner = trick_cards[0];
+        for played in trick_cards.iter().skip(1) {
+            if beats(&played.0, &winner.0, &self.contract) {
+                winner = played;
             }
-            return Ordering::Equal;
-        });
-
-        return if winner.is_some() {
-            Some(winner.unwrap().1)
-        } else {
-            None
-        };
+        }
+        Some(winner.1)
     }
 
     fn update_next_player(&mut self) {
@@ -234,104 +298,108 @@ impl Game {
     /// If player hand is unknown, pass None.
     /// In this case, almost all actions have to be considered valid and can only be checked after the game is over.
     pub fn action_is_valid(&self, action: &Card, hand: Option<&Hand>) -> bool {
-        let trick_cards = self
-            .played
+        match hand {
+            Some(hand) => self.get_legal_actions(hand).contains(&action),
+            None => !self.played.iter().any(|c| c.0 == *action),
+        }
+    }
+
+    pub fn get_legal_actions<'a>(&'a self, hand: &'a Hand) -> Vec<&'a Card> {
+        let legal = hand
+            .cards
             .iter()
-            .skip((self.trick * 4) as usize)
-            .take(4)
-            .map(|c| &c.0)
+            .filter(|c| self.follows_rules(c, hand))
             .collect::<Vec<&Card>>();
-        let player_is_called = match self.contract {
-            Contract::Call(suit) => {
-                return if hand.is_none() {
-                    false
-                } else {
-                    let hand = hand.unwrap();
-                    action.suit == suit
-                        && hand
-                            .cards
-                            .iter()
-                            .any(|c| c.suit == suit && c.value == Value::Ace)
-                }
-            }
-            _ => false,
-        };
-        let can_run = match self.contract {
-            Contract::Call(suit) => {
-                return if hand.is_none() {
-                    false
-                } else {
-                    let hand = hand.unwrap();
-                    return action.suit == suit
-                        && hand.cards.iter().filter(|c| c.suit == suit).count() >= 4;
-                }
-            }
-            _ => false,
-        };
-        let called_suit = match self.contract {
-            Contract::Call(suit) => Some(suit),
+        if legal.is_empty() {
+            // only happens if the called ace is the last card, which then has to be played
+            return hand.cards.iter().collect();
+        }
+        legal
+    }
+
+    /// The cards of the trick that is currently being played.
+    pub fn current_trick(&self) -> &[PlayedCard] {
+        let start = (self.played.len() / 4) * 4;
+        &self.played[start..]
+    }
+
+    /// True once the partner in a call game has run away from the called ace.
+    pub fn ran_away(&self) -> bool {
+        self.ran_away
+    }
+
+    fn follows_rules(&self, card: &Card, hand: &Hand) -> bool {
+        let called_ace = match self.contract {
+            Contract::Call(suit) => Some(Card {
+                suit,
+                value: Value::Ace,
+            }),
             _ => None,
         };
-
-        // check leading card validity
-        if trick_cards.len() == 0 {
-            if player_is_called {
-                if Some(action.suit) == called_suit && action.value == Value::Ace && !self.ran_away
-                {
-                    // player is called and has not run away -> can't lead with called ace
-                    return false;
-                }
-                if Some(action.suit) == called_suit && !self.ran_away && !can_run {
-                    // player is called and hasn't and isn't able to run away -> can't lead with called suit
-                    return false;
-                }
-                // card is not in called suit -> can lead with it
-                // player can run away -> can lead with anything but called ace
-                // player has run away -> can lead with anything
-                return true;
+        // the partner has to keep the called ace until its suit is played, unless they ran away
+        let ace_bound = called_ace.is_some_and(|ace| hand.cards.contains(&ace)) && !self.ran_away;
+        let is_called_ace = ace_bound && Some(*card) == called_ace;
+
+        let trick_cards = self.current_trick();
+        if trick_cards.is_empty() {
+            let called_suit = called_ace.map(|c| c.suit);
+            if ace_bound && !is_called_ace && effective_suit(card, &self.contract) == called_suit {
+                // leading the called suit without the ace is only allowed when running away
+                return hand
+                    .cards
+                    .iter()
+                    .filter(|c| effective_suit(c, &self.contract) == called_suit)
+                    .count()
+                    >= 4;
             }
+            return true;
         }
 
-        let leading_suit = trick_cards[0].suit;
-        let lead_is_trump = is_trump(trick_cards[0], &self.contract);
-
-        let has_leading_suit =
-            hand.is_some() && hand.unwrap().cards.iter().any(|c| c.suit == leading_suit);
-        let has_trump = hand.is_some()
-            && hand
-                .unwrap()
-                .cards
-                .iter()
-                .any(|c| is_trump(c, &self.contract));
-        let leading_suit_is_called = Some(leading_suit) == called_suit;
-
-        // check non leading card validity
-        return if lead_is_trump && has_trump {
-            // lead is trump -> can only play trump
-            is_trump(action, &self.contract)
-        } else if leading_suit_is_called && player_is_called && !self.ran_away {
-            // player is called and leading suit is called -> must play called ace unless ran away already
-            action.suit == leading_suit && action.value == Value::Ace
-        } else if has_leading_suit {
-            // player has leading suit -> must play leading suit
-            action.suit == leading_suit
-        } else {
-            // player doesn't have leading suit -> can play anything
-            true
-        };
+        let lead_suit = effective_suit(&trick_cards[0].0, &self.contract);
+        let card_suit = effective_suit(card, &self.contract);
+        if ace_bound && lead_suit.is_some() && lead_suit == called_ace.map(|c| c.suit) {
+            // called suit is searched -> partner has to give the ace
+            return is_called_ace;
+        }
+        if hand
+            .cards
+            .iter()
+            .any(|c| effective_suit(c, &self.contract) == lead_suit)
+        {
+            // player can follow -> has to follow
+            return card_suit == lead_suit;
+        }
+        // player can't follow -> anything but the called ace
+        !is_called_ace
     }
 
-    pub fn get_legal_actions<'a>(&'a self, hand: &'a Hand) -> Vec<&Card> {
-        hand.cards
-            .iter()
-            .filter(|c| self.action_is_valid(c, Some(hand)))
-            .collect()
+    /// Whether the player to move may play the card, like `get_legal_actions` but without allocating.
+    pub fn is_legal(&self, card: &Card) -> bool {
+        let hand = &self.hands[self.next_player];
+        hand.cards.contains(card)
+            && (self.follows_rules(card, hand)
+                || !hand.cards.iter().any(|c| self.follows_rules(c, hand)))
     }
 
-    pub fn play_card(&mut self, card: Card) {
-        if !self.is_ready_to_play() {
-            println!("Game is not ready to play yet");
-            return;
+    /// Plays the card for the player to move. Returns `false` and leaves the game as it is
+    /// if there's no contract yet, the game is over or the card isn't legal.
+    pub fn play_card(&mut self, card: Card) -> bool {
+        if self.contract == Contract::None || self.is_over() || !self.is_legal(&card) {
+            return false;
+        }
+        if let Contract::Call(suit) = self.contract {
+            let hand = &self.hands[self.next_player];
+            if self.current_trick().is_empty()
+                && card.suit == suit
+                && card.value != Value::Ace
+                && !is_trump(&card, &self.contract)
+                && hand.cards.contains(&Card {
+                    suit,
+                    value: Value::Ace,
+                })
+            {
+                self.ran_away = true;
+            }
         }
         self.played.push(PlayedCard(card, self.next_player));
         self.hands[self.next_player as usize].played.push(card);
@@ -339,6 +407,8 @@ impl Game {
             .cards
             .retain(|c| c != &card);
         self.update_next_player();
+        self.update_trick();
+        true
     }
 
     pub fn is_ready_to_play(&self) -> bool {
@@ -375,47 +445,196 @@ impl Game {
         points
     }
 
-    pub fn get_earnings(&self) -> [u32; 4] {
+    /// Number of tricks every player took.
+    pub fn get_tricks(&self) -> [u8; 4] {
+        let mut tricks = [0; 4];
+        for trick in 0..(self.played.len() / 4) as u8 {
+            if let Some(winner) = self.determine_trick_winner(trick) {
+                tricks[winner] += 1;
+            }
+        }
+        tricks
+    }
+
+    /// Players on the declaring side: the declarer and, in a call game, the owner of the called ace.
+    pub fn declaring_team(&self) -> [bool; 4] {
+        let mut team = [false; 4];
+        team[self.declarer] = true;
+        if let Contract::Call(suit) = self.contract {
+            let ace = Card {
+                suit,
+                value: Value::Ace,
+            };
+            if let Some(partner) = self
+                .hands
+                .iter()
+                .position(|h| h.cards.contains(&ace) || h.played.contains(&ace))
+            {
+                team[partner] = true;
+            }
+        }
+        team
+    }
+
+    /// Number of consecutive top trumps held by one side, counted from the highest trump.
+    pub fn get_laufende(&self) -> usize {
+        let team = self.declaring_team();
+        let holder = |card: &Card| {
+            self.hands
+                .iter()
+                .position(|h| h.cards.contains(card) || h.played.contains(card))
+                .map(|p| team[p])
+        };
+        let trumps = get_trump_order(&self.contract);
+        let top_side = match trumps.last() {
+            Some(card) => holder(card),
+            None => return 0,
+        };
+        trumps
+            .iter()
+            .rev()
+            .take_while(|c| holder(c) == top_side)
+            .count()
+    }
+
+    /// Settles a finished game: what every player receives (positive) or pays (negative).
+    pub fn get_earnings(&self, rules: &RuleSet) -> [i32; 4] {
         let points = self.get_points();
+        let tricks = self.get_tricks();
         let mut earnings = [0; 4];
-        match self.contract {
-            Contract::Call(suit) => {
-                let called_player = self
-                    .hands
-                    .iter()
-                    .position(|h| {
-                        h.played
-                            .iter()
-                            .any(|c| c.suit == suit && c.value == Value::Ace)
-                    })
-                    .unwrap();
-
-                let player_points = points[called_player] + points[self.declarer];
+
+        if self.contract == Contract::Ramsch {
+            let tariff = rules.tariff(&self.contract);
+            if let Some(winner) = tricks.iter().position(|t| *t == 8) {
+                // Durchmarsch: one player took every trick
+                for (player, earning) in earnings.iter_mut().enumerate() {
+                    *earning = if player == winner {
+                        3 * tariff
+                    } else {
+                        -tariff
+                    };
+                }
+                return earnings;
+            }
+            let most = *points.iter().max().unwrap();
+            let losers = points.iter().filter(|p| **p == most).count() as i32;
+            for (player, earning) in earnings.iter_mut().enumerate() {
+                *earning = if points[player] == most {
+                    -(4 - losers) * tariff
+                } else {
+                    losers * tariff
+                };
             }
-            _ => todo!(),
+            return earnings;
         }
 
+        let team = self.declaring_team();
+        let team_points: u32 = (0..4).filter(|p| team[*p]).map(|p| points[p] as u32).sum();
+        let team_tricks: u32 = (0..4).filter(|p| team[*p]).map(|p| tricks[p] as u32).sum();
+        let declarer_wins = team_points > 60;
+        let loser_points = if declarer_wins {
+            120 - team_points
+        } else {
+            team_points
+        };
+        let loser_tricks = if declarer_wins {
+            8 - team_tricks
+        } else {
+            team_tricks
+        };
+
+        let mut value = rules.tariff(&self.contract);
+        if loser_points <= 30 {
+            value += rules.schneider_bonus;
+        }
+        if loser_tricks == 0 {
+            value += rules.schwarz_bonus;
+        }
+        let laufende = self.get_laufende();
+        if laufende >= rules.min_laufende(&self.contract) {
+            value += laufende as i32 * rules.laufende_bonus;
+        }
+        if !declarer_wins {
+            value = -value;
+        }
+
+        let team_size = team.iter().filter(|t| **t).count() as i32;
+        for (player, earning) in earnings.iter_mut().enumerate() {
+            // every opponent pays (or receives) the value, split evenly among the declaring side
+            *earning = if team[player] {
+                value * (4 - team_size) / team_size
+            } else {
+                -value
+            };
+        }
         earnings
     }
 }
 
+/// Suit a card has to be followed with, None if the card is a trump.
+pub fn effective_suit(card: &Card, contract: &Contract) -> Option<Suit> {
+    if is_trump(card, contract) {
+        None
+    } else {
+        Some(card.suit)
+    }
+}
+
+/// Rank of a card within its suit when it is not a trump, higher is stronger.
+pub fn plain_rank(card: &Card) -> u8 {
+    match card.value {
+        Value::Seven => 0,
+        Value::Eight => 1,
+        Value::Nine => 2,
+        Value::Under => 3,
+        Value::Ober => 4,
+        Value::King => 5,
+        Value::Ten => 6,
+        Value::Ace => 7,
+    }
+}
+
+/// Returns true if `card` beats `best`, the card currently winning the trick.
+pub fn beats(card: &Card, best: &Card, contract: &Contract) -> bool {
+    match (trump_rank(card, contract), trump_rank(best, contract)) {
+        (Some(a), Some(b)) => a > b,
+        (Some(_), None) => true,
+        (None, Some(_)) => false,
+        (None, None) => card.suit == best.suit && plain_rank(card) > plain_rank(best),
+    }
+}
+
+/// Returns all trumps of the given contract, ordered from lowest to highest.
 pub fn get_trump_order(contract: &Contract) -> Vec<Card> {
-    let deck = Card::deck();
-    let mut trumps = deck
+    let mut trumps = Card::all()
         .into_iter()
         .filter(|c| is_trump(c, contract))
         .collect::<Vec<Card>>();
-    trumps.sort_by(|a, b| {
-        let mut cmp = a.value.cmp(&b.value);
-        if cmp == Ordering::Equal {
-            cmp = a.suit.cmp(&b.suit);
-        }
-        return cmp;
-    });
+    trumps.sort_by_key(|c| trump_rank(c, contract));
 
     trumps
 }
 
+/// Rank of a trump card within the given contract, higher is stronger.
+/// Obers beat Unters, which beat the remaining trumps of the trump suit.
+/// Returns None if the card is not a trump.
+pub fn trump_rank(card: &Card, contract: &Contract) -> Option<u8> {
+    if !is_trump(card, contract) {
+        return None;
+    }
+    let rank = match card.value {
+        Value::Ober => 12 + card.suit as u8,
+        Value::Under => 8 + card.suit as u8,
+        Value::Ace => 5,
+        Value::Ten => 4,
+        Value::King => 3,
+        Value::Nine => 2,
+        Value::Eight => 1,
+        Value::Seven => 0,
+    };
+    Some(rank)
+}
+
 struct Dealer {
     deck: Vec<Card>,
 }
@@ -451,11 +670,13 @@ pub struct PlayerGameState<'a> {
     pub hand: &'a Hand,
     pub contract: Contract,
     pub player_nr: usize,
+    pub forehand: usize,
+    pub declarer: usize,
     pub trick: &'a u8,
     pub played: &'a Vec<PlayedCard>,
 }
 
-fn is_trump(card: &Card, contract: &Contract) -> bool {
+pub fn is_trump(card: &Card, contract: &Contract) -> bool {
     match contract {
         Contract::Call(_) | Contract::Ramsch => {
             card.suit == Suit::Hearts || card.value == Value::Ober || card.value == Value::Under
@@ -468,12 +689,21 @@ fn is_trump(card: &Card, contract: &Contract) -> bool {
     }
 }
 
-#[derive(Debug)]
+/// A single announcement made during an auction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuctionAction {
+    Intent(bool),
+    Bid(Contract),
+}
+
+#[derive(Clone, Debug)]
 pub struct Auction {
     pub highest_bid: Contract,
     pub highest_bidder: usize,
     pub next_bidder: Option<usize>,
     pub intent: [bool; 4],
+    /// Every announcement so far together with the player who made it, in order.
+    pub history: Vec<(usize, AuctionAction)>,
     intent_count: u8,
 }
 
@@ -484,6 +714,7 @@ impl Auction {
             highest_bidder: starting_bidder,
             next_bidder: Some(starting_bidder),
             intent: [false; 4],
+            history: Vec::new(),
             intent_count: 0,
         }
     }
@@ -555,7 +786,9 @@ impl Auction {
         if self.intent_count == 4 {
             return;
         }
-        self.intent[self.next_bidder.expect("Bidder must exist at this point.") as usize] = intent;
+        let bidder = self.next_bidder.expect("Bidder must exist at this point.");
+        self.intent[bidder] = intent;
+        self.history.push((bidder, AuctionAction::Intent(intent)));
         self.intent_count += 1;
         self.update_next_bidder();
     }
@@ -593,6 +826,8 @@ impl Auction {
 
     pub fn bid(&mut self, bid: Contract) {
         assert!(self.valid_bids(None).contains(&bid));
+        let bidder = self.next_bidder.expect("Bidder must exist at this point.");
+        self.history.push((bidder, AuctionAction::Bid(bid)));
         if let Contract::None = bid {
             self.intent[self.next_bidder.expect("Bidder must exist at this point.") as usize] =
                 false;
@@ -680,6 +915,128 @@ mod tests {
         assert_eq!(game.hands[3].cards.len(), 8);
     }
 
+    fn card(suit: Suit, value: Value) -> Card {
+        Card { suit, value }
+    }
+
+    fn call_game() -> Game {
+        let mut game = Game::from_hands(
+            0,
+            [
+                vec![
+                    card(Suit::Acorns, Value::Seven),
+                    card(Suit::Hearts, Value::Ace),
+                    card(Suit::Leaves, Value::Ten),
+                ],
+                vec![
+                    card(Suit::Acorns, Value::Ace),
+                    card(Suit::Acorns, Value::King),
+                    card(Suit::Bells, Value::Seven),
+                ],
+                vec![
+                    card(Suit::Acorns, Value::Ober),
+                    card(Suit::Bells, Value::Ace),
+                    card(Suit::Leaves, Value::Seven),
+                ],
+                vec![
+                    card(Suit::Hearts, Value::Seven),
+                    card(Suit::Leaves, Value::Ace),
+                    card(Suit::Bells, Value::Ten),
+                ],
+            ],
+        );
+        game.contract = Contract::Call(Suit::Acorns);
+        game
+    }
+
+    #[test]
+    fn test_trump_order() {
+        let trumps = get_trump_order(&Contract::Call(Suit::Acorns));
+        assert_eq!(trumps.len(), 14);
+        assert_eq!(trumps[13], card(Suit::Acorns, Value::Ober));
+        assert_eq!(trumps[5], card(Suit::Hearts, Value::Ace));
+        assert_eq!(trumps[0], card(Suit::Hearts, Value::Seven));
+    }
+
+    #[test]
+    fn test_card_index() {
+        for (index, card) in Card::all().iter().enumerate() {
+            assert_eq!(card.index(), index);
+            assert_eq!(Card::from_index(index), *card);
+        }
+    }
+
+    #[test]
+    fn test_called_ace_has_to_be_played() {
+        let mut game = call_game();
+        assert!(game.play_card(card(Suit::Acorns, Value::Seven)));
+        let legal = game.get_legal_actions(&game.hands[1]);
+        assert_eq!(legal, vec![&card(Suit::Acorns, Value::Ace)]);
+        assert!(!game.play_card(card(Suit::Acorns, Value::Seven)));
+        assert_eq!(game.played.len(), 1);
+    }
+
+    #[test]
+    fn test_called_ace_cant_be_discarded() {
+        let mut game = call_game();
+        game.play_card(card(Suit::Leaves, Value::Ten));
+        let legal = game.get_legal_actions(&game.hands[1]);
+        assert_eq!(
+            legal,
+            vec![
+                &card(Suit::Acorns, Value::King),
+                &card(Suit::Bells, Value::Seven)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trick_winner_and_next_trick() {
+        let mut game = call_game();
+        game.play_card(card(Suit::Hearts, Value::Ace));
+        game.play_card(card(Suit::Bells, Value::Seven));
+        game.play_card(card(Suit::Acorns, Value::Ober));
+        game.play_card(card(Suit::Hearts, Value::Seven));
+        // the ober beats the ace of hearts
+        assert_eq!(game.next_player, 2);
+        assert_eq!(game.get_points(), [0, 0, 14, 0]);
+        assert!(game.current_trick().is_empty());
+        game.play_card(card(Suit::Leaves, Value::Seven));
+        assert_eq!(game.current_trick().len(), 1);
+        assert_eq!(game.next_player, 3);
+    }
+
+    #[test]
+    fn test_earnings_sum_to_zero() {
+        let rules = RuleSet::default();
+        for contract in [
+            Contract::Call(Suit::Leaves),
+            Contract::Solo(Suit::Bells),
+            Contract::Wenz,
+            Contract::Ramsch,
+        ] {
+            for _ in 0..20 {
+                let mut game = Game::new(0);
+                game.contract = contract;
+                game.declarer = 1;
+                while !game.is_over() {
+                    let card = *game.get_legal_actions(&game.hands[game.next_player])[0];
+                    game.play_card(card);
+                }
+                let earnings = game.get_earnings(&rules);
+                assert_eq!(earnings.iter().sum::<i32>(), 0);
+                if contract != Contract::Ramsch {
+                    let team = game.declaring_team();
+                    let points: u32 = (0..4)
+                        .filter(|p| team[*p])
+                        .map(|p| game.get_points()[p] as u32)
+                        .sum();
+                    assert_eq!(earnings[1] > 0, points > 60);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_call_matches_any() {
         let contract = Contract::Call(Suit::Acorns);
@@ -732,6 +1089,28 @@ mod tests {
         assert!(auction.valid_bids(None).is_empty(),);
     }
 
+    #[test]
+    fn test_auction_history() {
+        let mut auction = Auction::new(1);
+        for intent in [true, false, false, true] {
+            auction.announce_intent(intent);
+        }
+        auction.bid(Contract::Call(Suit::Bells));
+        auction.bid(Contract::None);
+        assert!(auction.is_finished());
+        assert_eq!(
+            auction.history,
+            vec![
+                (1, AuctionAction::Intent(true)),
+                (2, AuctionAction::Intent(false)),
+                (3, AuctionAction::Intent(false)),
+                (0, AuctionAction::Intent(true)),
+                (1, AuctionAction::Bid(Contract::Call(Suit::Bells))),
+                (0, AuctionAction::Bid(Contract::None)),
+            ]
+        );
+    }
+
     use proptest::prelude::*;
     proptest! {
 
@@ -1,78 +1,835 @@
-use crate::schafkopf_env::agent::{Agent, RandomAgent};
-use crate::schafkopf_env::game_logic::{Auction, Game};
+use crate::schafkopf_env::agent::Agent;
+use crate::schafkopf_env::game_logic::{
+    Auction, Card, Contract, Game, PlayedCard, PlayerGameState, RuleSet,
+};
+use crate::schafkopf_env::observer::{MatchEvent, Observer};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::fmt;
+use std::fmt::Formatter;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::{Duration, Instant};
 
+/// Reasons a match can't do what it was asked to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MatchError {
+    /// Four players are registered already.
+    Full,
+    /// Fewer than four players are registered.
+    NotReady,
+    /// The match was stopped.
+    Stopped,
+    /// There's no player in the given seat.
+    NoSuchSeat(usize),
+    /// The current hand has to be finished first.
+    HandInProgress,
+    /// No hand is being played.
+    NoHand,
+    /// The remote player in the seat has to decide first.
+    Waiting(usize),
+    /// The player's response doesn't answer the request or isn't allowed, or the player
+    /// is an agent, which decides by itself.
+    InvalidResponse(usize),
+    /// The given hands aren't four hands of eight different cards.
+    InvalidDeal,
+}
+
+impl fmt::Display for MatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchError::Full => write!(f, "Match is full"),
+            MatchError::NotReady => write!(f, "Match is not ready"),
+            MatchError::Stopped => write!(f, "Match is stopped"),
+            MatchError::NoSuchSeat(seat) => write!(f, "No player in seat {}", seat),
+            MatchError::HandInProgress => write!(f, "A hand is still being played"),
+            MatchError::NoHand => write!(f, "No hand is being played"),
+            MatchError::Waiting(seat) => write!(f, "Waiting for the player in seat {}", seat),
+            MatchError::InvalidResponse(seat) => {
+                write!(f, "Invalid response from the player in seat {}", seat)
+            }
+            MatchError::InvalidDeal => write!(f, "Invalid deal"),
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
+
+/// Outcome of a single hand of a match.
+#[derive(Clone, Debug)]
+pub struct HandResult {
+    pub forehand: usize,
+    pub auction: Auction,
+    pub contract: Contract,
+    pub declarer: usize,
+    /// Card points every player took.
+    pub points: [u8; 4],
+    /// What every player won or lost in this hand.
+    pub earnings: [i32; 4],
+    /// Everything that went wrong with the agents during the hand.
+    pub faults: Vec<Fault>,
+    /// The player whose fault ended the hand early, see `FaultPolicy::ForfeitHand`.
+    pub forfeited_by: Option<usize>,
+}
+
+/// What went wrong when an agent was asked for a decision.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    Panicked,
+    /// The agent answered, but later than the match's time limit.
+    TimedOut,
+    /// The agent answered with something it isn't allowed to do.
+    InvalidAction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub player: usize,
+    pub kind: FaultKind,
+}
+
+/// What the match does when an agent faults.
+///
+/// An agent that never answers can't be faulted, agents run on the match's thread and
+/// can't be interrupted. Only `SubprocessAgent` bounds its own decisions, by giving up on
+/// a bot that doesn't answer in time.
+pub enum FaultPolicy {
+    /// The hand ends at once and the player pays every other player the solo tariff.
+    ForfeitHand,
+    /// A random legal card or bid is played for the player, intents are declined.
+    RandomAction,
+    /// The agent is replaced by one made by the factory, for the rest of the match.
+    /// If the replacement faults as well, a random action is taken.
+    Substitute(Box<dyn FnMut() -> Box<dyn Agent>>),
+}
+
+/// Who decides for a seat.
+pub enum Seat {
+    /// Asked directly whenever a decision is due.
+    Agent(Box<dyn Agent>),
+    /// Decides from the outside, e.g. a human in a UI or a player on the network.
+    /// The match waits for a `Response` instead of blocking.
+    Remote,
+}
+
+/// A decision the match is waiting for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    Intent {
+        player: usize,
+    },
+    Bid {
+        player: usize,
+        valid_bids: Vec<Contract>,
+    },
+    Play {
+        player: usize,
+        legal_plays: Vec<Card>,
+    },
+}
+
+impl Request {
+    /// Whether the response answers the request with something allowed.
+    pub fn allows(&self, response: &Response) -> bool {
+        match (self, response) {
+            (Request::Intent { .. }, Response::Intent(_)) => true,
+            (Request::Bid { valid_bids, .. }, Response::Bid(bid)) => valid_bids.contains(bid),
+            (Request::Play { legal_plays, .. }, Response::Play(card)) => legal_plays.contains(card),
+            _ => false,
+        }
+    }
+
+    /// A random allowed response, passing whenever possible.
+    pub fn random_response(&self) -> Response {
+        let mut rng = thread_rng();
+        match self {
+            Request::Intent { .. } => Response::Intent(false),
+            Request::Bid { valid_bids, .. } if valid_bids.contains(&Contract::None) => {
+                Response::Bid(Contract::None)
+            }
+            Request::Bid { valid_bids, .. } => Response::Bid(
+                *valid_bids
+                    .choose(&mut rng)
+                    .expect("A bid must be possible."),
+            ),
+            Request::Play { legal_plays, .. } => Response::Play(
+                *legal_plays
+                    .choose(&mut rng)
+                    .expect("A card must be playable."),
+            ),
+        }
+    }
+
+    /// The player who has to decide.
+    pub fn player(&self) -> usize {
+        match self {
+            Request::Intent { player }
+            | Request::Bid { player, .. }
+            | Request::Play { player, .. } => *player,
+        }
+    }
+}
+
+/// Answer to a `Request`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    Intent(bool),
+    Bid(Contract),
+    Play(Card),
+}
+
+/// Where the current hand stands after the match has done all it could on its own.
+#[derive(Clone, Debug)]
+pub enum Progress {
+    /// A remote seat has to decide before the hand can go on.
+    Waiting(Request),
+    HandOver(HandResult),
+}
+
+/// The hand currently being played.
+struct Deal {
+    game: Game,
+    auction: Auction,
+    faults: Vec<Fault>,
+}
+
+/// Four players playing hand after hand, with forehand moving on every hand.
+///
+/// Hands can be played in one go with `play_game` if every seat is an agent. With remote
+/// seats, the match is driven step by step instead: `start_hand` deals, `poll` lets the
+/// agents decide until a remote seat is due, and `respond` passes that seat's decision on.
+///
+/// Agents aren't trusted: panics are caught, answers are validated and slow answers are
+/// discarded, after which `fault_policy` decides how the hand goes on. Remote seats just
+/// get their invalid responses rejected.
 pub struct Match {
-    pub score: [u32; 4],
+    /// Money every player won or lost over all hands so far.
+    pub score: [i32; 4],
     pub games: Vec<Game>,
-    players: Vec<Box<dyn Agent>>,
+    pub rules: RuleSet,
+    /// Longest an agent may take for a decision. Agents can't be interrupted, so this
+    /// only takes effect once they answer, see `FaultPolicy`.
+    pub time_limit: Option<Duration>,
+    pub fault_policy: FaultPolicy,
+    players: Vec<Seat>,
+    observers: Vec<Box<dyn Observer>>,
+    deal: Option<Deal>,
     stopped: bool,
     next_forehand: usize,
 }
 
+impl Default for Match {
+    fn default() -> Match {
+        Match::new()
+    }
+}
+
 impl Match {
-    fn new() -> Match {
+    pub fn new() -> Match {
+        Match::with_rules(RuleSet::default())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> Match {
         Match {
             score: [0; 4],
             games: Vec::new(),
+            rules,
+            time_limit: None,
+            fault_policy: FaultPolicy::RandomAction,
             players: Vec::new(),
+            observers: Vec::new(),
+            deal: None,
             stopped: false,
             next_forehand: 0,
         }
     }
 
-    fn register_player(&mut self, player: Box<dyn Agent>) {
+    /// Seats the player at the next free seat and returns the seat.
+    pub fn register_player(&mut self, player: Box<dyn Agent>) -> Result<usize, MatchError> {
+        self.register_seat(Seat::Agent(player))
+    }
+
+    /// Seats a player who answers requests from the outside and returns the seat.
+    pub fn register_remote(&mut self) -> Result<usize, MatchError> {
+        self.register_seat(Seat::Remote)
+    }
+
+    fn register_seat(&mut self, seat: Seat) -> Result<usize, MatchError> {
         if self.players.len() == 4 {
-            println!("Match is full");
-            return;
+            return Err(MatchError::Full);
         }
-        self.players.push(player);
+        self.players.push(seat);
+        Ok(self.players.len() - 1)
+    }
+
+    /// Puts another player into the seat, e.g. when somebody leaves the table.
+    pub fn replace_player(
+        &mut self,
+        player: Box<dyn Agent>,
+        index: usize,
+    ) -> Result<(), MatchError> {
+        self.replace_seat(Seat::Agent(player), index)
     }
 
-    fn replace_player(&mut self, player: Box<dyn Agent>, index: usize) {
+    /// Like `replace_player`, but also lets a remote player take over from an agent.
+    /// Works in the middle of a hand, the new seat makes the next decision.
+    pub fn replace_seat(&mut self, seat: Seat, index: usize) -> Result<(), MatchError> {
         if index >= self.players.len() {
-            println!("Index out of bounds");
-            return;
+            return Err(MatchError::NoSuchSeat(index));
         }
-        self.players[index] = player;
+        self.players[index] = seat;
+        Ok(())
     }
 
-    fn is_ready(&self) -> bool {
+    pub fn is_ready(&self) -> bool {
         self.players.len() == 4
     }
 
-    fn play_game(&mut self) {
-        if !self.is_ready() || self.stopped {
-            println!("Match is not ready");
+    /// Tells the observer about everything that happens from now on.
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Ends the match and tells the observers, no more hands can be played afterwards.
+    pub fn stop(&mut self) {
+        if self.stopped {
             return;
         }
+        self.stopped = true;
+        self.notify(MatchEvent::MatchFinished {
+            hands: self.games.len(),
+            score: self.score,
+        });
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// The player who leads the next hand.
+    pub fn next_forehand(&self) -> usize {
+        self.next_forehand
+    }
+
+    /// Plays `hands` hands in a row. The match goes on until it is stopped.
+    pub fn play(&mut self, hands: usize) -> Result<Vec<HandResult>, MatchError> {
+        (0..hands).map(|_| self.play_game()).collect()
+    }
+
+    /// Deals and plays a single hand, settles it and moves forehand on.
+    /// Fails with `MatchError::Waiting` as soon as a remote seat has to decide.
+    pub fn play_game(&mut self) -> Result<HandResult, MatchError> {
+        self.start_hand()?;
+        self.play_out()
+    }
+
+    /// Like `play_game`, but with the given hands instead of a fresh deal.
+    pub fn play_deal(&mut self, hands: [Vec<Card>; 4]) -> Result<HandResult, MatchError> {
+        self.start_hand_with(hands)?;
+        self.play_out()
+    }
+
+    fn play_out(&mut self) -> Result<HandResult, MatchError> {
+        match self.poll()? {
+            Progress::HandOver(result) => Ok(result),
+            Progress::Waiting(request) => Err(MatchError::Waiting(request.player())),
+        }
+    }
+
+    /// Deals the next hand.
+    pub fn start_hand(&mut self) -> Result<(), MatchError> {
+        self.begin_hand(None)
+    }
+
+    /// Starts the next hand with the given hands, e.g. to replay a known deal.
+    pub fn start_hand_with(&mut self, hands: [Vec<Card>; 4]) -> Result<(), MatchError> {
+        let mut seen = [false; 32];
+        for card in hands.iter().flatten() {
+            if seen[card.index()] {
+                return Err(MatchError::InvalidDeal);
+            }
+            seen[card.index()] = true;
+        }
+        if hands.iter().any(|h| h.len() != 8) {
+            return Err(MatchError::InvalidDeal);
+        }
+        self.begin_hand(Some(hands))
+    }
+
+    fn begin_hand(&mut self, hands: Option<[Vec<Card>; 4]>) -> Result<(), MatchError> {
+        if self.stopped {
+            return Err(MatchError::Stopped);
+        }
+        if !self.is_ready() {
+            return Err(MatchError::NotReady);
+        }
+        if self.deal.is_some() {
+            return Err(MatchError::HandInProgress);
+        }
 
-        let mut game = Game::new(self.next_forehand);
-        let mut player_index = Some(self.next_forehand);
+        let forehand = self.next_forehand;
+        let game = match hands {
+            Some(hands) => Game::from_hands(forehand, hands),
+            None => Game::new(forehand),
+        };
+        self.notify(MatchEvent::Deal {
+            hand_nr: self.games.len(),
+            forehand,
+            hands: game.hands.clone().map(|h| h.cards),
+        });
+        self.deal = Some(Deal {
+            game,
+            auction: Auction::new(forehand),
+            faults: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Lets the agents decide until a remote seat is due or the hand is over.
+    pub fn poll(&mut self) -> Result<Progress, MatchError> {
+        loop {
+            let request = match self.request() {
+                Some(request) => request,
+                None => return self.finish_hand(None).map(Progress::HandOver),
+            };
+            let player = request.player();
+            let deal = self.deal.as_ref().ok_or(MatchError::NoHand)?;
+            let answer = match &mut self.players[player] {
+                Seat::Remote => return Ok(Progress::Waiting(request)),
+                Seat::Agent(agent) => ask(agent.as_mut(), &request, deal, self.time_limit),
+            };
+            let response = match answer {
+                Ok(response) => response,
+                Err(kind) => {
+                    let fault = Fault { player, kind };
+                    if let Some(deal) = self.deal.as_mut() {
+                        deal.faults.push(fault);
+                    }
+                    self.notify(MatchEvent::Fault(fault));
+                    match self.recover(&request)? {
+                        Some(response) => response,
+                        None => return self.finish_hand(Some(player)).map(Progress::HandOver),
+                    }
+                }
+            };
+            self.apply(response)?;
+        }
+    }
 
-        let mut auction = Auction::new(self.next_forehand);
-        while !auction.bidding_phase_started() && player_index.is_some() {
-            let player = game.get_player_game_state(player_index.unwrap());
-            let bid = self.players[player_index.unwrap()].get_bid(&player, &auction);
-            player_index = auction.next_bidder;
+    /// The decision the current hand is waiting for, if any.
+    pub fn request(&self) -> Option<Request> {
+        let deal = self.deal.as_ref()?;
+        if !deal.auction.is_finished() {
+            let player = deal.auction.next_bidder?;
+            return Some(if deal.auction.bidding_phase_started() {
+                Request::Bid {
+                    player,
+                    valid_bids: deal.auction.valid_bids(Some(&deal.game.hands[player])),
+                }
+            } else {
+                Request::Intent { player }
+            });
         }
+        if deal.game.is_over() {
+            return None;
+        }
+        let player = deal.game.next_player;
+        Some(Request::Play {
+            player,
+            legal_plays: deal
+                .game
+                .get_legal_actions(&deal.game.hands[player])
+                .into_iter()
+                .copied()
+                .collect(),
+        })
+    }
 
-        while !auction.is_finished() && player_index.is_some() {
-            let player_state = game.get_player_game_state(player_index.unwrap());
-            auction.bid(self.players[player_index.unwrap()].get_bid(&player_state, &auction));
-            player_index = auction.next_bidder;
+    /// The current hand as the player sees it, e.g. to render it.
+    pub fn player_state(&self, player: usize) -> Option<PlayerGameState<'_>> {
+        self.deal
+            .as_ref()
+            .map(|deal| deal.game.get_player_game_state(player))
+    }
+
+    /// The auction of the current hand.
+    pub fn auction(&self) -> Option<&Auction> {
+        self.deal.as_ref().map(|deal| &deal.auction)
+    }
+
+    /// Carries out the decision of the remote player the current hand is waiting for.
+    /// Agents decide through `poll`, answering for them fails.
+    pub fn respond(&mut self, response: Response) -> Result<(), MatchError> {
+        let request = self.request().ok_or(MatchError::NoHand)?;
+        let player = request.player();
+        if !matches!(self.players.get(player), Some(Seat::Remote)) {
+            return Err(MatchError::InvalidResponse(player));
         }
+        self.apply(response)
+    }
 
-        game.contract = auction.winning_contract();
-        game.declarer = auction.highest_bidder;
-        assert!(game.is_ready_to_play());
+    /// Carries out the decision the current hand is waiting for, whoever made it.
+    fn apply(&mut self, response: Response) -> Result<(), MatchError> {
+        let request = self.request().ok_or(MatchError::NoHand)?;
+        let deal = self.deal.as_mut().ok_or(MatchError::NoHand)?;
+        let player = request.player();
+        if !request.allows(&response) {
+            return Err(MatchError::InvalidResponse(player));
+        }
+        let event = match response {
+            Response::Intent(intent) => {
+                deal.auction.announce_intent(intent);
+                MatchEvent::Intent { player, intent }
+            }
+            Response::Bid(contract) => {
+                deal.auction.bid(contract);
+                MatchEvent::Bid { player, contract }
+            }
+            Response::Play(card) => {
+                deal.game.play_card(card);
+                MatchEvent::CardPlayed { player, card }
+            }
+        };
+        self.notify(event);
 
-        while !game.is_over() && player_index.is_some() {
-            let player_state = game.get_player_game_state(player_index.unwrap());
-            let legal_plays = game.get_legal_actions(&game.hands[player_index.unwrap()]);
-            game.play_card(
-                self.players[player_index.unwrap()].get_play(&player_state, legal_plays),
-            );
-            player_index = Some(game.next_player);
+        let deal = self.deal.as_mut().ok_or(MatchError::NoHand)?;
+        if deal.auction.is_finished() && deal.game.contract == Contract::None {
+            deal.game.contract = deal.auction.winning_contract();
+            deal.game.declarer = deal.auction.highest_bidder;
+            assert!(deal.game.is_ready_to_play());
+            let event = MatchEvent::ContractDecided {
+                contract: deal.game.contract,
+                declarer: deal.game.declarer,
+            };
+            self.notify(event);
+        } else if matches!(response, Response::Play(_)) && deal.game.played.len().is_multiple_of(4)
+        {
+            let trick: [PlayedCard; 4] = deal.game.played[deal.game.played.len() - 4..]
+                .try_into()
+                .expect("A trick has four cards.");
+            let event = MatchEvent::TrickWon {
+                winner: deal.game.next_player,
+                points: trick.iter().map(|c| c.0.points()).sum(),
+                trick,
+            };
+            self.notify(event);
         }
+        Ok(())
+    }
+
+    /// Applies the fault policy to a request the agent failed to answer.
+    /// Returns `None` if the hand is forfeited.
+    fn recover(&mut self, request: &Request) -> Result<Option<Response>, MatchError> {
+        let player = request.player();
+        let factory = match &mut self.fault_policy {
+            FaultPolicy::ForfeitHand => return Ok(None),
+            FaultPolicy::RandomAction => return Ok(Some(request.random_response())),
+            FaultPolicy::Substitute(factory) => factory,
+        };
+        let mut agent = factory();
+        let deal = self.deal.as_ref().ok_or(MatchError::NoHand)?;
+        let response = ask(agent.as_mut(), request, deal, self.time_limit)
+            .unwrap_or_else(|_| request.random_response());
+        self.replace_player(agent, player)?;
+        Ok(Some(response))
+    }
+
+    /// Settles the hand and moves forehand on. A forfeited hand is settled
+    /// without looking at the cards.
+    fn finish_hand(&mut self, forfeited_by: Option<usize>) -> Result<HandResult, MatchError> {
+        let Deal {
+            game,
+            auction,
+            faults,
+        } = self.deal.take().ok_or(MatchError::NoHand)?;
+        let earnings = match forfeited_by {
+            Some(offender) => {
+                let mut earnings = [self.rules.solo_tariff; 4];
+                earnings[offender] = -3 * self.rules.solo_tariff;
+                earnings
+            }
+            None => game.get_earnings(&self.rules),
+        };
+        for (score, earning) in self.score.iter_mut().zip(earnings.iter()) {
+            *score += earning;
+        }
+        if forfeited_by.is_none() {
+            for (player_nr, seat) in self.players.iter_mut().enumerate() {
+                if let Seat::Agent(agent) = seat {
+                    agent.game_over(player_nr, &auction, &game);
+                }
+            }
+        }
+        self.next_forehand = (game.forehand + 1) % 4;
+
+        let result = HandResult {
+            forehand: game.forehand,
+            contract: game.contract,
+            declarer: game.declarer,
+            points: game.get_points(),
+            earnings,
+            auction,
+            faults,
+            forfeited_by,
+        };
+        self.games.push(game);
+        self.notify(MatchEvent::HandSettled {
+            result: result.clone(),
+            score: self.score,
+        });
+        Ok(result)
+    }
+
+    fn notify(&mut self, event: MatchEvent) {
+        for observer in self.observers.iter_mut() {
+            observer.notify(&event);
+        }
+    }
+}
+
+/// Asks the agent to answer the request and checks the answer.
+fn ask(
+    agent: &mut dyn Agent,
+    request: &Request,
+    deal: &Deal,
+    time_limit: Option<Duration>,
+) -> Result<Response, FaultKind> {
+    let state = deal.game.get_player_game_state(request.player());
+    // only taken with a limit, `Instant` isn't available in the browser
+    let start = time_limit.map(|limit| (Instant::now(), limit));
+    let response = catch_unwind(AssertUnwindSafe(|| match request {
+        Request::Intent { .. } => Response::Intent(agent.get_intent(&state, &deal.auction)),
+        Request::Bid { .. } => Response::Bid(agent.get_bid(&state, &deal.auction)),
+        Request::Play { player, .. } => {
+            let legal_plays = deal.game.get_legal_actions(&deal.game.hands[*player]);
+            Response::Play(agent.get_play(&state, legal_plays))
+        }
+    }))
+    .map_err(|_| FaultKind::Panicked)?;
+    if start.is_some_and(|(start, limit)| start.elapsed() > limit) {
+        return Err(FaultKind::TimedOut);
+    }
+    if !request.allows(&response) {
+        return Err(FaultKind::InvalidAction);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::agent::RandomAgent;
+
+    fn full_match() -> Match {
+        let mut m = Match::new();
+        for seat in 0..4 {
+            assert_eq!(m.register_player(Box::new(RandomAgent::new())), Ok(seat));
+        }
+        m
+    }
+
+    #[test]
+    fn test_match_plays_and_scores_hands() {
+        let mut m = full_match();
+        assert_eq!(
+            m.register_player(Box::new(RandomAgent::new())),
+            Err(MatchError::Full)
+        );
+        let results = m.play(8).unwrap();
+        assert_eq!(results.len(), 8);
+        assert_eq!(m.games.len(), 8);
+        for (hand, result) in results.iter().enumerate() {
+            assert_eq!(result.forehand, hand % 4);
+            assert_eq!(result.earnings.iter().sum::<i32>(), 0);
+            assert_eq!(result.points.iter().map(|p| *p as u32).sum::<u32>(), 120);
+        }
+        let mut total = [0; 4];
+        for result in results.iter() {
+            for (t, e) in total.iter_mut().zip(result.earnings.iter()) {
+                *t += e;
+            }
+        }
+        assert_eq!(m.score, total);
+        assert_eq!(m.next_forehand(), 0);
+    }
+
+    #[test]
+    fn test_observers_follow_the_match() {
+        let mut m = full_match();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        m.add_observer(Box::new(sender));
+        let results = m.play(2).unwrap();
+        m.stop();
+        m.stop();
+
+        let events = receiver.try_iter().collect::<Vec<MatchEvent>>();
+        let count = |f: fn(&MatchEvent) -> bool| events.iter().filter(|e| f(e)).count();
+        assert_eq!(count(|e| matches!(e, MatchEvent::Deal { .. })), 2);
+        assert_eq!(count(|e| matches!(e, MatchEvent::Intent { .. })), 8);
+        assert_eq!(
+            count(|e| matches!(e, MatchEvent::ContractDecided { .. })),
+            2
+        );
+        assert_eq!(count(|e| matches!(e, MatchEvent::CardPlayed { .. })), 64);
+        assert_eq!(count(|e| matches!(e, MatchEvent::TrickWon { .. })), 16);
+        assert_eq!(count(|e| matches!(e, MatchEvent::HandSettled { .. })), 2);
+        assert_eq!(count(|e| matches!(e, MatchEvent::MatchFinished { .. })), 1);
+
+        let trick_points = events
+            .iter()
+            .filter_map(|e| match e {
+                MatchEvent::TrickWon { winner, points, .. } => Some((*winner, *points)),
+                _ => None,
+            })
+            .take(8)
+            .fold([0u8; 4], |mut points, (winner, p)| {
+                points[winner] += p;
+                points
+            });
+        assert_eq!(trick_points, results[0].points);
+        assert!(
+            matches!(events.last(), Some(MatchEvent::MatchFinished { hands: 2, score })
+            if *score == m.score)
+        );
+    }
+
+    #[test]
+    fn test_remote_seat_is_driven_by_responses() {
+        let mut m = Match::new();
+        for _ in 0..3 {
+            m.register_player(Box::new(RandomAgent::new())).unwrap();
+        }
+        let remote = m.register_remote().unwrap();
+        for _ in 0..4 {
+            m.start_hand().unwrap();
+            assert_eq!(m.start_hand(), Err(MatchError::HandInProgress));
+            let request = m.request().unwrap();
+            if request.player() != remote {
+                assert_eq!(
+                    m.respond(request.random_response()),
+                    Err(MatchError::InvalidResponse(request.player()))
+                );
+            }
+            let result = loop {
+                match m.poll().unwrap() {
+                    Progress::HandOver(result) => break result,
+                    Progress::Waiting(request) => {
+                        assert_eq!(request.player(), remote);
+                        let response = match request {
+                            Request::Intent { .. } => Response::Intent(false),
+                            Request::Bid { valid_bids, .. } => Response::Bid(valid_bids[0]),
+                            Request::Play { legal_plays, .. } => {
+                                let illegal = Card::all()
+                                    .into_iter()
+                                    .find(|c| !legal_plays.contains(c))
+                                    .unwrap();
+                                assert_eq!(
+                                    m.respond(Response::Play(illegal)),
+                                    Err(MatchError::InvalidResponse(remote))
+                                );
+                                assert_eq!(
+                                    m.respond(Response::Intent(true)),
+                                    Err(MatchError::InvalidResponse(remote))
+                                );
+                                Response::Play(legal_plays[0])
+                            }
+                        };
+                        m.respond(response).unwrap();
+                    }
+                }
+            };
+            assert_eq!(result.earnings.iter().sum::<i32>(), 0);
+        }
+        assert_eq!(m.games.len(), 4);
+        assert_eq!(m.poll().err(), Some(MatchError::NoHand));
+        m.start_hand().unwrap();
+        assert!(m.player_state(remote).is_some());
+    }
+
+    /// Breaks in a different way every time it has to play a card.
+    struct Faulty {
+        plays: usize,
+    }
+
+    impl Agent for Faulty {
+        fn get_intent(&mut self, _state: &PlayerGameState, _auction: &Auction) -> bool {
+            false
+        }
+
+        fn get_bid(&mut self, _state: &PlayerGameState, _auction: &Auction) -> Contract {
+            Contract::None
+        }
+
+        fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+            self.plays += 1;
+            match self.plays % 3 {
+                0 => panic!("faulty agent"),
+                1 => {
+                    std::thread::sleep(Duration::from_millis(500));
+                    *legal_plays[0]
+                }
+                _ => *state.played.first().map_or(legal_plays[0], |p| &p.0),
+            }
+        }
+    }
+
+    fn faulty_match(policy: FaultPolicy) -> Match {
+        let mut m = Match::new();
+        // far from what the random agents take, even on a busy machine
+        m.time_limit = Some(Duration::from_millis(200));
+        m.fault_policy = policy;
+        m.register_player(Box::new(Faulty { plays: 0 })).unwrap();
+        for _ in 0..3 {
+            m.register_player(Box::new(RandomAgent::new())).unwrap();
+        }
+        m
+    }
+
+    #[test]
+    fn test_faults_are_caught() {
+        let mut m = faulty_match(FaultPolicy::RandomAction);
+        let result = m.play_game().unwrap();
+        assert!(m.games[0].is_over());
+        assert_eq!(result.forfeited_by, None);
+        assert!(result.faults.iter().all(|f| f.player == 0));
+        for kind in [
+            FaultKind::Panicked,
+            FaultKind::TimedOut,
+            FaultKind::InvalidAction,
+        ] {
+            assert!(result.faults.iter().any(|f| f.kind == kind));
+        }
+    }
+
+    #[test]
+    fn test_fault_policies() {
+        let mut m = faulty_match(FaultPolicy::ForfeitHand);
+        let result = m.play_game().unwrap();
+        assert_eq!(result.forfeited_by, Some(0));
+        assert_eq!(result.faults.len(), 1);
+        assert_eq!(result.earnings, [-150, 50, 50, 50]);
+        assert_eq!(m.score, result.earnings);
+        assert_eq!(m.next_forehand(), 1);
+
+        let mut m = faulty_match(FaultPolicy::Substitute(Box::new(|| {
+            Box::new(RandomAgent::new())
+        })));
+        let first = m.play_game().unwrap();
+        assert_eq!(first.faults.len(), 1);
+        let second = m.play_game().unwrap();
+        assert!(second.faults.is_empty());
+    }
+
+    #[test]
+    fn test_match_needs_four_players() {
+        let mut m = Match::new();
+        m.register_player(Box::new(RandomAgent::new())).unwrap();
+        assert_eq!(m.play_game().err(), Some(MatchError::NotReady));
+        assert_eq!(
+            m.replace_player(Box::new(RandomAgent::new()), 2),
+            Err(MatchError::NoSuchSeat(2))
+        );
+        let mut m = full_match();
+        m.stop();
+        assert_eq!(m.play_game().err(), Some(MatchError::Stopped));
     }
 }
@@ -1,3 +1,10 @@
 pub mod agent;
+pub mod console;
 pub mod game_logic;
 pub mod match_logic;
+pub mod observer;
+pub mod protocol;
+pub mod rating;
+pub mod statistics;
+pub mod subprocess;
+pub mod tournament;
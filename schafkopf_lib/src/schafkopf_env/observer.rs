@@ -0,0 +1,67 @@
+use crate::schafkopf_env::game_logic::{Card, Contract, PlayedCard};
+use crate::schafkopf_env::match_logic::{Fault, HandResult};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+/// Everything that happens in a match, in the order it happens.
+#[derive(Clone, Debug)]
+pub enum MatchEvent {
+    /// A new hand was dealt. Contains every hand, so only show players their own.
+    Deal {
+        hand_nr: usize,
+        forehand: usize,
+        hands: [Vec<Card>; 4],
+    },
+    Intent {
+        player: usize,
+        intent: bool,
+    },
+    Bid {
+        player: usize,
+        contract: Contract,
+    },
+    /// The auction is over, `Contract::Ramsch` if nobody wanted to play.
+    ContractDecided {
+        contract: Contract,
+        declarer: usize,
+    },
+    CardPlayed {
+        player: usize,
+        card: Card,
+    },
+    /// An agent failed to make a decision, the match's fault policy takes over.
+    Fault(Fault),
+    TrickWon {
+        winner: usize,
+        points: u8,
+        trick: [PlayedCard; 4],
+    },
+    HandSettled {
+        result: HandResult,
+        score: [i32; 4],
+    },
+    MatchFinished {
+        hands: usize,
+        score: [i32; 4],
+    },
+}
+
+/// Follows a match as it is played, e.g. to render, log or collect statistics.
+pub trait Observer {
+    fn notify(&mut self, event: &MatchEvent);
+}
+
+/// Lets the owner keep a handle on the observer and read from it during or after the match.
+impl<O: Observer> Observer for Rc<RefCell<O>> {
+    fn notify(&mut self, event: &MatchEvent) {
+        self.borrow_mut().notify(event);
+    }
+}
+
+/// Forwards the events to another thread. Events are dropped once the receiver is gone.
+impl Observer for Sender<MatchEvent> {
+    fn notify(&mut self, event: &MatchEvent) {
+        let _ = self.send(event.clone());
+    }
+}
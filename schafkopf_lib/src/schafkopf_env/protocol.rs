@@ -0,0 +1,220 @@
+//! Line-based JSON protocol for bots running outside the engine.
+//!
+//! The engine writes one JSON object per line and, for decisions, reads one JSON object
+//! per line back. Every message has a `"type"`:
+//!
+//! - `intent`: `{"type":"intent","observation":{..}}`, answered with `{"intent":true}`
+//! - `bid`: `{"type":"bid","observation":{..},"valid_bids":["Wenz","Pass",..]}`,
+//!   answered with `{"bid":"Wenz"}`
+//! - `play`: `{"type":"play","observation":{..},"legal_plays":["Acorns Ace",..]}`,
+//!   answered with `{"card":"Acorns Ace"}`
+//! - `game_over`: `{"type":"game_over","player":0,"contract":"Wenz","declarer":2,
+//!   "hands":[[..],..],"played":[..],"auction":[..],"points":[..]}`, not answered
+//!
+//! The observation is what the player knows: `player`, `forehand`, `hand`, `contract`,
+//! `declarer`, the cards `played` so far as `{"player":1,"card":"Bells Ten"}` and, while
+//! bidding, the `auction` as `{"player":1,"intent":true}` and `{"player":1,"bid":"Wenz"}`.
+//! Cards are named `"<Suit> <Value>"` with the suits Acorns, Leaves, Hearts, Bells and the
+//! values Seven, Eight, Nine, Ten, Under, Ober, King, Ace. Contracts are named `"Call <Suit>"`,
+//! `"Solo <Suit>"`, `"Wenz"`, `"Ramsch"` and `"Pass"` for no contract.
+
+use crate::schafkopf_env::game_logic::{
+    Auction, AuctionAction, Card, Contract, Game, PlayedCard, PlayerGameState, Suit,
+};
+use crate::schafkopf_env::match_logic::{Request, Response};
+use serde_json::{json, Value as Json};
+use std::fmt;
+use std::fmt::Formatter;
+use strum::IntoEnumIterator;
+
+/// A reply that can't be understood.
+#[derive(Debug)]
+pub enum ProtocolError {
+    InvalidJson(serde_json::Error),
+    /// Valid JSON, but not an answer to the request.
+    UnexpectedReply(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::InvalidJson(error) => write!(f, "Invalid JSON: {}", error),
+            ProtocolError::UnexpectedReply(reply) => write!(f, "Unexpected reply: {}", reply),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+pub fn card_name(card: &Card) -> String {
+    format!("{:?} {:?}", card.suit, card.value)
+}
+
+pub fn parse_card(name: &str) -> Option<Card> {
+    Card::all().into_iter().find(|c| card_name(c) == name)
+}
+
+pub fn contract_name(contract: &Contract) -> String {
+    match contract {
+        Contract::Call(suit) => format!("Call {:?}", suit),
+        Contract::Solo(suit) => format!("Solo {:?}", suit),
+        Contract::Wenz => "Wenz".to_string(),
+        Contract::Ramsch => "Ramsch".to_string(),
+        Contract::None => "Pass".to_string(),
+    }
+}
+
+pub fn parse_contract(name: &str) -> Option<Contract> {
+    Suit::iter()
+        .flat_map(|suit| [Contract::Call(suit), Contract::Solo(suit)])
+        .chain([Contract::Wenz, Contract::Ramsch, Contract::None])
+        .find(|c| contract_name(c) == name)
+}
+
+fn cards<'a, I: IntoIterator<Item = &'a Card>>(cards: I) -> Json {
+    cards.into_iter().map(card_name).collect()
+}
+
+fn played(played: &[PlayedCard]) -> Json {
+    played
+        .iter()
+        .map(|PlayedCard(card, player)| json!({"player": player, "card": card_name(card)}))
+        .collect()
+}
+
+fn auction_history(auction: &Auction) -> Json {
+    auction
+        .history
+        .iter()
+        .map(|(player, action)| match action {
+            AuctionAction::Intent(intent) => json!({"player": player, "intent": intent}),
+            AuctionAction::Bid(bid) => json!({"player": player, "bid": contract_name(bid)}),
+        })
+        .collect()
+}
+
+/// What the player knows about the hand, the auction only while it's going on.
+pub fn observation(state: &PlayerGameState, auction: Option<&Auction>) -> Json {
+    let mut observation = json!({
+        "player": state.player_nr,
+        "forehand": state.forehand,
+        "hand": cards(&state.hand.cards),
+        "contract": contract_name(&state.contract),
+        "declarer": state.declarer,
+        "played": played(state.played),
+    });
+    if let Some(auction) = auction {
+        observation["auction"] = auction_history(auction);
+    }
+    observation
+}
+
+/// Asks for a decision.
+pub fn request_message(
+    request: &Request,
+    state: &PlayerGameState,
+    auction: Option<&Auction>,
+) -> Json {
+    let observation = observation(state, auction);
+    match request {
+        Request::Intent { .. } => json!({"type": "intent", "observation": observation}),
+        Request::Bid { valid_bids, .. } => json!({
+            "type": "bid",
+            "observation": observation,
+            "valid_bids": valid_bids.iter().map(contract_name).collect::<Vec<String>>(),
+        }),
+        Request::Play { legal_plays, .. } => json!({
+            "type": "play",
+            "observation": observation,
+            "legal_plays": cards(legal_plays),
+        }),
+    }
+}
+
+/// Reads the answer to `request`. Whether the answer is allowed is up to `Request::allows`.
+pub fn parse_response(line: &str, request: &Request) -> Result<Response, ProtocolError> {
+    let reply: Json = serde_json::from_str(line).map_err(ProtocolError::InvalidJson)?;
+    let response = match request {
+        Request::Intent { .. } => reply["intent"].as_bool().map(Response::Intent),
+        Request::Bid { .. } => reply["bid"]
+            .as_str()
+            .and_then(parse_contract)
+            .map(Response::Bid),
+        Request::Play { .. } => reply["card"]
+            .as_str()
+            .and_then(parse_card)
+            .map(Response::Play),
+    };
+    response.ok_or_else(|| ProtocolError::UnexpectedReply(line.trim().to_string()))
+}
+
+/// Tells the player how a hand ended, with every hand as it was dealt.
+pub fn game_over_message(player: usize, auction: &Auction, game: &Game) -> Json {
+    let hands = game
+        .hands
+        .iter()
+        .map(|h| cards(h.played.iter().chain(h.cards.iter())))
+        .collect::<Vec<Json>>();
+    json!({
+        "type": "game_over",
+        "player": player,
+        "contract": contract_name(&game.contract),
+        "declarer": game.declarer,
+        "hands": hands,
+        "played": played(&game.played),
+        "auction": auction_history(auction),
+        "points": game.get_points(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for card in Card::all() {
+            assert_eq!(parse_card(&card_name(&card)), Some(card));
+        }
+        let mut contracts = Auction::new(0).valid_bids(None);
+        contracts.push(Contract::Ramsch);
+        for contract in contracts {
+            assert_eq!(parse_contract(&contract_name(&contract)), Some(contract));
+        }
+        assert_eq!(parse_card("Hearts Joker"), None);
+    }
+
+    #[test]
+    fn test_requests_and_responses() {
+        let mut game = Game::new(1);
+        let auction = Auction::new(1);
+        let state = game.get_player_game_state(1);
+        let request = Request::Bid {
+            player: 1,
+            valid_bids: vec![Contract::Wenz, Contract::None],
+        };
+        let message = request_message(&request, &state, Some(&auction));
+        assert_eq!(message["type"], "bid");
+        assert_eq!(message["observation"]["hand"].as_array().unwrap().len(), 8);
+        assert_eq!(message["valid_bids"], json!(["Wenz", "Pass"]));
+        assert_eq!(
+            parse_response("{\"bid\":\"Pass\"}", &request).unwrap(),
+            Response::Bid(Contract::None)
+        );
+        assert!(matches!(
+            parse_response("{\"card\":\"Pass\"}", &request),
+            Err(ProtocolError::UnexpectedReply(_))
+        ));
+        assert!(matches!(
+            parse_response("pass", &request),
+            Err(ProtocolError::InvalidJson(_))
+        ));
+
+        game.contract = Contract::Wenz;
+        let card = game.hands[1].cards[0];
+        game.play_card(card);
+        let message = game_over_message(1, &auction, &game);
+        assert_eq!(message["played"][0]["card"], card_name(&card));
+        assert_eq!(message["hands"][1][0], card_name(&card));
+    }
+}
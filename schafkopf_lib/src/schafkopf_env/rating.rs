@@ -0,0 +1,330 @@
+use crate::schafkopf_env::game_logic::Contract;
+use crate::schafkopf_env::match_logic::HandResult;
+use crate::schafkopf_env::observer::{MatchEvent, Observer};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const HEADER: &str = "# schafkopf ratings v1";
+pub const INITIAL_RATING: f64 = 1500.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    /// Number of rated hands.
+    pub hands: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating {
+            rating: INITIAL_RATING,
+            hands: 0,
+        }
+    }
+}
+
+/// Elo ratings of bots and humans, updated hand by hand.
+///
+/// A hand is rated as a match between the side that won money and the side that lost it,
+/// so the call partners share a result and a soloist plays against the average of all three
+/// opponents. Rating points are zero-sum: what a soloist wins, the opponents split. A Ramsch
+/// is rated as a game of everybody against everybody, ranked by earnings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ratings {
+    /// How far a single hand can move a rating.
+    pub k: f64,
+    players: BTreeMap<String, Rating>,
+}
+
+impl Default for Ratings {
+    fn default() -> Ratings {
+        Ratings::new()
+    }
+}
+
+impl Ratings {
+    pub fn new() -> Ratings {
+        Ratings {
+            k: 16.0,
+            players: BTreeMap::new(),
+        }
+    }
+
+    /// The player's rating, the initial one for unknown players.
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.get(name).copied().unwrap_or_default()
+    }
+
+    /// Everybody rated so far, best first.
+    pub fn leaderboard(&self) -> Vec<(&str, Rating)> {
+        let mut board = self
+            .players
+            .iter()
+            .map(|(name, rating)| (name.as_str(), *rating))
+            .collect::<Vec<(&str, Rating)>>();
+        board.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        board
+    }
+
+    /// Rates a settled hand, `names` being the players in seat order.
+    ///
+    /// A name in several seats, e.g. when fewer than four entrants play a tournament, is one
+    /// player with the earnings of all its seats, and is rated against the others only.
+    pub fn update(&mut self, names: [&str; 4], result: &HandResult) {
+        let mut players: Vec<(&str, i32)> = Vec::new();
+        for (name, earnings) in names.into_iter().zip(result.earnings) {
+            match players.iter_mut().find(|(n, _)| *n == name) {
+                Some(player) => player.1 += earnings,
+                None => players.push((name, earnings)),
+            }
+        }
+        let count = players.len();
+        if count < 2 {
+            return;
+        }
+        let ratings = players
+            .iter()
+            .map(|(name, _)| self.get(name).rating)
+            .collect::<Vec<f64>>();
+        let mut changes = vec![0.0; count];
+        if result.contract == Contract::Ramsch {
+            let k = self.k / (count - 1) as f64;
+            for a in 0..count {
+                for b in a + 1..count {
+                    let score = match players[a].1.cmp(&players[b].1) {
+                        Ordering::Greater => 1.0,
+                        Ordering::Equal => 0.5,
+                        Ordering::Less => 0.0,
+                    };
+                    let change = k * (score - expected(ratings[a], ratings[b]));
+                    changes[a] += change;
+                    changes[b] -= change;
+                }
+            }
+        } else {
+            // a player that won as much as it lost on the other side sits the hand out
+            let won = players
+                .iter()
+                .map(|(_, earnings)| (*earnings != 0).then_some(*earnings > 0))
+                .collect::<Vec<Option<bool>>>();
+            let size = |side: bool| won.iter().filter(|w| **w == Some(side)).count() as f64;
+            let mean = |side: bool| {
+                (0..count)
+                    .filter(|p| won[*p] == Some(side))
+                    .map(|p| ratings[p])
+                    .sum::<f64>()
+                    / size(side)
+            };
+            let (winners, losers) = (size(true), size(false));
+            if winners > 0.0 && losers > 0.0 {
+                let change = self.k * (1.0 - expected(mean(true), mean(false)));
+                // the smaller side takes the whole change, the larger one shares it
+                let smaller = winners.min(losers);
+                for (player, won) in won.iter().enumerate() {
+                    changes[player] = match won {
+                        Some(true) => change * smaller / winners,
+                        Some(false) => -change * smaller / losers,
+                        None => 0.0,
+                    };
+                }
+            }
+        }
+        for ((name, _), change) in players.into_iter().zip(changes) {
+            let rating = self.players.entry(name.to_string()).or_default();
+            rating.rating += change;
+            rating.hands += 1;
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Ratings> {
+        Ratings::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Writes one line per player: the rating, the number of hands and the name.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "k {}", self.k)?;
+        for (name, rating) in self.players.iter() {
+            writeln!(writer, "{} {} {}", rating.rating, rating.hands, name)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Ratings> {
+        let mut lines = BufReader::new(reader).lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid("not a ratings file"));
+        }
+        let mut ratings = Ratings::new();
+        let k = lines.next().transpose()?.unwrap_or_default();
+        ratings.k = k
+            .strip_prefix("k ")
+            .and_then(|k| k.parse().ok())
+            .ok_or_else(|| invalid("missing k factor"))?;
+        for line in lines {
+            let line = line?;
+            let mut fields = line.splitn(3, ' ');
+            let rating = fields.next().and_then(|r| r.parse().ok());
+            let hands = fields.next().and_then(|h| h.parse().ok());
+            match (rating, hands, fields.next()) {
+                (Some(rating), Some(hands), Some(name)) => {
+                    ratings
+                        .players
+                        .insert(name.to_string(), Rating { rating, hands });
+                }
+                _ => return Err(invalid("malformed rating")),
+            }
+        }
+        Ok(ratings)
+    }
+}
+
+impl fmt::Display for Ratings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (rank, (name, rating)) in self.leaderboard().into_iter().enumerate() {
+            writeln!(
+                f,
+                "{}. {}: {:.0} ({} hands)",
+                rank + 1,
+                name,
+                rating.rating,
+                rating.hands
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected score of a player rated `a` against one rated `b`.
+fn expected(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Rates every hand of a match as it is settled.
+pub struct RatingObserver {
+    pub ratings: Ratings,
+    /// The players in seat order.
+    pub names: [String; 4],
+}
+
+impl Observer for RatingObserver {
+    fn notify(&mut self, event: &MatchEvent) {
+        if let MatchEvent::HandSettled { result, .. } = event {
+            let names = [0, 1, 2, 3].map(|seat| self.names[seat].as_str());
+            self.ratings.update(names, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::game_logic::{Auction, Suit};
+
+    fn result(contract: Contract, earnings: [i32; 4]) -> HandResult {
+        HandResult {
+            forehand: 0,
+            auction: Auction::new(0),
+            contract,
+            declarer: 0,
+            points: [0; 4],
+            earnings,
+            faults: Vec::new(),
+            forfeited_by: None,
+        }
+    }
+
+    fn total(ratings: &Ratings) -> f64 {
+        ratings.leaderboard().iter().map(|(_, r)| r.rating).sum()
+    }
+
+    #[test]
+    fn test_updates_are_zero_sum() {
+        let mut ratings = Ratings::new();
+        let names = ["anna", "bot-v1", "bot-v2", "carl"];
+        ratings.update(
+            names,
+            &result(Contract::Call(Suit::Acorns), [20, -20, 20, -20]),
+        );
+        assert_eq!(ratings.get("anna").rating, INITIAL_RATING + 8.0);
+        assert_eq!(ratings.get("bot-v1").rating, INITIAL_RATING - 8.0);
+        ratings.update(
+            names,
+            &result(Contract::Solo(Suit::Hearts), [-150, 50, 50, 50]),
+        );
+        let before = ratings.get("bot-v2").rating;
+        ratings.update(names, &result(Contract::Ramsch, [10, 10, -30, 10]));
+        assert!((total(&ratings) - 4.0 * INITIAL_RATING).abs() < 1e-9);
+        assert!(ratings.get("bot-v2").rating < before);
+        assert_eq!(ratings.get("carl").hands, 3);
+        assert_eq!(ratings.get("nobody"), Rating::default());
+    }
+
+    #[test]
+    fn test_soloist_takes_the_whole_change() {
+        let mut ratings = Ratings::new();
+        let names = ["a", "b", "c", "d"];
+        ratings.update(
+            names,
+            &result(Contract::Solo(Suit::Bells), [150, -50, -50, -50]),
+        );
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING + 8.0);
+        assert!((ratings.get("b").rating - (INITIAL_RATING - 8.0 / 3.0)).abs() < 1e-9);
+        assert_eq!(ratings.leaderboard()[0].0, "a");
+        assert!(ratings.to_string().starts_with("1. a: 1508 (1 hands)"));
+    }
+
+    #[test]
+    fn test_ratings_round_trip() {
+        let mut ratings = Ratings::new();
+        ratings.k = 24.0;
+        let names = ["Anna Maier", "bot v1", "b", "c"];
+        ratings.update(names, &result(Contract::Wenz, [-50, 150, -50, -50]));
+        let mut file = Vec::new();
+        ratings.write_to(&mut file).unwrap();
+        assert_eq!(Ratings::read_from(&mut file.as_slice()).unwrap(), ratings);
+        assert!(Ratings::read_from(&mut "1500 0 anna".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_repeated_names_are_one_player() {
+        let mut ratings = Ratings::new();
+        let names = ["a", "b", "a", "b"];
+        // a called b's ace and both won, so a has beaten b twice over
+        ratings.update(
+            names,
+            &result(Contract::Call(Suit::Acorns), [20, -20, 20, -20]),
+        );
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING + 8.0);
+        assert_eq!(ratings.get("a").hands, 1);
+        // a played with one seat of b against the other, nobody won
+        ratings.update(
+            names,
+            &result(Contract::Call(Suit::Bells), [20, 20, -20, -20]),
+        );
+        assert_eq!(ratings.get("a").rating, INITIAL_RATING + 8.0);
+        ratings.update(names, &result(Contract::Ramsch, [10, 10, -30, 10]));
+        assert!(ratings.get("a").rating < INITIAL_RATING + 8.0);
+        assert!((total(&ratings) - 2.0 * INITIAL_RATING).abs() < 1e-9);
+        assert_eq!(ratings.get("b").hands, 3);
+
+        ratings.update(["c"; 4], &result(Contract::Wenz, [150, -50, -50, -50]));
+        assert_eq!(ratings.get("c"), Rating::default());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::schafkopf_env::game_logic::{Auction, AuctionAction, Contract, Game};
+use crate::schafkopf_env::observer::{MatchEvent, Observer};
+use serde_json::{json, Value as Json};
+use std::fmt::Write;
+
+/// How a single contract fared. Ramsch has no declaring side, so it only counts as played.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ContractStats {
+    pub contract: Contract,
+    /// Number of bids for the contract, whether they won the auction or not.
+    pub bid: usize,
+    pub played: usize,
+    /// Games the declaring side won.
+    pub won: usize,
+    /// Card points of the declaring side, summed over all games.
+    pub points: u64,
+    /// Games in which the losing side took 30 points or less.
+    pub schneider: usize,
+    /// Games in which the losing side took no trick.
+    pub schwarz: usize,
+}
+
+impl ContractStats {
+    fn new(contract: Contract) -> ContractStats {
+        ContractStats {
+            contract,
+            bid: 0,
+            played: 0,
+            won: 0,
+            points: 0,
+            schneider: 0,
+            schwarz: 0,
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.won, self.played)
+    }
+
+    /// Average card points of the declaring side.
+    pub fn average_points(&self) -> f64 {
+        self.points as f64 / self.played.max(1) as f64
+    }
+
+    pub fn schneider_rate(&self) -> f64 {
+        ratio(self.schneider, self.played)
+    }
+
+    pub fn schwarz_rate(&self) -> f64 {
+        ratio(self.schwarz, self.played)
+    }
+}
+
+/// Results by seat, counted from forehand: seat 0 is forehand, seat 3 the dealer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeatStats {
+    /// Hands the player in this seat was on the winning side of.
+    pub won: usize,
+    pub declared: usize,
+    pub declared_won: usize,
+}
+
+/// Statistics over many finished games, e.g. to check whether bots bid too loosely or how a
+/// rule change shifts the balance. Feed it games with `record`, or add it to a match as an
+/// observer.
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    pub hands: usize,
+    /// Every contract, including Ramsch, in a fixed order.
+    pub contracts: Vec<ContractStats>,
+    /// Games by number of Laufende of the declaring side, Ramsch excluded.
+    pub laufende: Vec<usize>,
+    pub seats: [SeatStats; 4],
+    /// The hand currently followed as an observer.
+    current: Option<Game>,
+}
+
+impl Default for Statistics {
+    fn default() -> Statistics {
+        Statistics::new()
+    }
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        let mut contracts = Auction::new(0)
+            .valid_bids(None)
+            .into_iter()
+            .filter(|c| *c != Contract::None)
+            .map(ContractStats::new)
+            .collect::<Vec<ContractStats>>();
+        contracts.push(ContractStats::new(Contract::Ramsch));
+        Statistics {
+            hands: 0,
+            contracts,
+            laufende: Vec::new(),
+            seats: [SeatStats::default(); 4],
+            current: None,
+        }
+    }
+
+    pub fn contract(&self, contract: Contract) -> Option<&ContractStats> {
+        self.contracts.iter().find(|c| c.contract == contract)
+    }
+
+    fn contract_mut(&mut self, contract: Contract) -> Option<&mut ContractStats> {
+        self.contracts.iter_mut().find(|c| c.contract == contract)
+    }
+
+    /// Share of the hands nobody wanted to play.
+    pub fn ramsch_frequency(&self) -> f64 {
+        let ramsch = self.contract(Contract::Ramsch).map_or(0, |c| c.played);
+        ratio(ramsch, self.hands)
+    }
+
+    /// Adds a finished game and the auction that decided its contract.
+    /// Games that aren't over yet are left out.
+    pub fn record(&mut self, auction: &Auction, game: &Game) {
+        if !game.is_over() {
+            return;
+        }
+        for (_, action) in auction.history.iter() {
+            if let AuctionAction::Bid(contract) = action {
+                if let Some(stats) = self.contract_mut(*contract) {
+                    stats.bid += 1;
+                }
+            }
+        }
+        self.hands += 1;
+        let seat = |player: usize| (player + 4 - game.forehand) % 4;
+        let points = game.get_points();
+        let tricks = game.get_tricks();
+
+        if game.contract == Contract::Ramsch {
+            if let Some(stats) = self.contract_mut(Contract::Ramsch) {
+                stats.played += 1;
+            }
+            let durchmarsch = tricks.iter().position(|t| *t == 8);
+            let most = *points.iter().max().unwrap();
+            for (player, points) in points.iter().enumerate() {
+                let won = match durchmarsch {
+                    Some(winner) => player == winner,
+                    None => *points < most,
+                };
+                if won {
+                    self.seats[seat(player)].won += 1;
+                }
+            }
+            return;
+        }
+
+        let team = game.declaring_team();
+        let team_points: u64 = (0..4).filter(|p| team[*p]).map(|p| points[p] as u64).sum();
+        let team_tricks: u8 = (0..4).filter(|p| team[*p]).map(|p| tricks[p]).sum();
+        let won = team_points > 60;
+        let (loser_points, loser_tricks) = if won {
+            (120 - team_points, 8 - team_tricks)
+        } else {
+            (team_points, team_tricks)
+        };
+        if let Some(stats) = self.contract_mut(game.contract) {
+            stats.played += 1;
+            stats.won += won as usize;
+            stats.points += team_points;
+            stats.schneider += (loser_points <= 30) as usize;
+            stats.schwarz += (loser_tricks == 0) as usize;
+        }
+
+        let laufende = game.get_laufende();
+        if self.laufende.len() <= laufende {
+            self.laufende.resize(laufende + 1, 0);
+        }
+        self.laufende[laufende] += 1;
+
+        let declarer = &mut self.seats[seat(game.declarer)];
+        declarer.declared += 1;
+        declarer.declared_won += won as usize;
+        for (player, declaring) in team.iter().enumerate() {
+            if *declaring == won {
+                self.seats[seat(player)].won += 1;
+            }
+        }
+    }
+
+    /// One line per contract, with a header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "contract,bid,played,won,win_rate,average_points,schneider_rate,schwarz_rate\n",
+        );
+        for c in self.contracts.iter() {
+            let _ = writeln!(
+                csv,
+                "{:?},{},{},{},{:.4},{:.2},{:.4},{:.4}",
+                c.contract,
+                c.bid,
+                c.played,
+                c.won,
+                c.win_rate(),
+                c.average_points(),
+                c.schneider_rate(),
+                c.schwarz_rate()
+            );
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let contracts = self
+            .contracts
+            .iter()
+            .map(|c| {
+                json!({
+                    "contract": format!("{:?}", c.contract),
+                    "bid": c.bid,
+                    "played": c.played,
+                    "won": c.won,
+                    "average_points": c.average_points(),
+                    "schneider": c.schneider,
+                    "schwarz": c.schwarz,
+                })
+            })
+            .collect::<Vec<Json>>();
+        let seats = self
+            .seats
+            .iter()
+            .map(|s| json!({"won": s.won, "declared": s.declared, "declared_won": s.declared_won}))
+            .collect::<Vec<Json>>();
+        json!({
+            "hands": self.hands,
+            "ramsch_frequency": self.ramsch_frequency(),
+            "contracts": contracts,
+            "laufende": self.laufende,
+            "seats": seats,
+        })
+        .to_string()
+    }
+}
+
+/// Replays the hands of a match to record them once they are settled.
+/// Hands that were forfeited before the last trick are left out.
+impl Observer for Statistics {
+    fn notify(&mut self, event: &MatchEvent) {
+        match event {
+            MatchEvent::Deal {
+                forehand, hands, ..
+            } => self.current = Some(Game::from_hands(*forehand, hands.clone())),
+            MatchEvent::ContractDecided { contract, declarer } => {
+                if let Some(game) = self.current.as_mut() {
+                    game.contract = *contract;
+                    game.declarer = *declarer;
+                }
+            }
+            MatchEvent::CardPlayed { card, .. } => {
+                if let Some(game) = self.current.as_mut() {
+                    game.play_card(*card);
+                }
+            }
+            MatchEvent::HandSettled { result, .. } => {
+                if let Some(game) = self.current.take().filter(|g| g.is_over()) {
+                    self.record(&result.auction, &game);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    count as f64 / total.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::agent::RandomAgent;
+    use crate::schafkopf_env::match_logic::Match;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_statistics_follow_a_match() {
+        let mut m = Match::new();
+        for seed in 0..4 {
+            m.register_player(Box::new(RandomAgent::with_seed(seed)))
+                .unwrap();
+        }
+        let stats = Rc::new(RefCell::new(Statistics::new()));
+        m.add_observer(Box::new(stats.clone()));
+        let results = m.play(40).unwrap();
+
+        let stats = stats.borrow();
+        assert_eq!(stats.hands, 40);
+        assert_eq!(stats.contracts.iter().map(|c| c.played).sum::<usize>(), 40);
+        let ramsch = results
+            .iter()
+            .filter(|r| r.contract == Contract::Ramsch)
+            .count();
+        assert_eq!(stats.ramsch_frequency(), ramsch as f64 / 40.0);
+        assert_eq!(stats.laufende.iter().sum::<usize>(), 40 - ramsch);
+        let declared = stats.seats.iter().map(|s| s.declared).sum::<usize>();
+        assert_eq!(declared, 40 - ramsch);
+        for c in stats.contracts.iter() {
+            assert!(c.won <= c.played && c.schwarz <= c.schneider);
+            assert!(c.played == 0 || c.bid > 0 || c.contract == Contract::Ramsch);
+        }
+
+        let csv = stats.to_csv();
+        assert_eq!(csv.lines().count(), stats.contracts.len() + 1);
+        assert!(csv.contains("\nWenz,"));
+        let json: Json = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["hands"], 40);
+        assert_eq!(
+            json["contracts"].as_array().unwrap().len(),
+            stats.contracts.len()
+        );
+        assert_eq!(
+            json["laufende"].as_array().unwrap().len(),
+            stats.laufende.len()
+        );
+        assert_eq!(json["seats"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_unfinished_games_are_left_out() {
+        let mut stats = Statistics::new();
+        stats.record(&Auction::new(0), &Game::new(0));
+        assert_eq!(stats.hands, 0);
+        assert!(stats.contracts.iter().all(|c| c.bid == 0 && c.played == 0));
+    }
+}
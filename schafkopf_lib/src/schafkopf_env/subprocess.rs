@@ -0,0 +1,222 @@
+use crate::schafkopf_env::agent::Agent;
+use crate::schafkopf_env::game_logic::{Auction, Card, Contract, Game, PlayerGameState};
+use crate::schafkopf_env::match_logic::{Request, Response};
+use crate::schafkopf_env::protocol::{game_over_message, parse_response, request_message};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An agent played by another program, talking the `protocol` over its stdin and stdout.
+///
+/// The `Agent` trait has no way to report errors, so a program that dies, answers
+/// something it isn't allowed to or takes longer than `timeout` makes the agent panic.
+/// `Match` catches that and handles it with its fault policy.
+pub struct SubprocessAgent {
+    /// Longest the program may take for a decision, `None` to wait forever.
+    pub timeout: Option<Duration>,
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<String>,
+    /// Requests that timed out, the program still answers them before the next one.
+    late: usize,
+}
+
+impl SubprocessAgent {
+    /// Starts the program, e.g. `SubprocessAgent::spawn(Command::new("python3").arg("bot.py"))`.
+    pub fn spawn(command: &mut Command) -> io::Result<SubprocessAgent> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        // read on a thread of its own, so waiting for a reply can time out
+        let (sender, replies) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(SubprocessAgent {
+            timeout: Some(Duration::from_secs(10)),
+            child,
+            stdin,
+            replies,
+            late: 0,
+        })
+    }
+
+    fn send(&mut self, message: serde_json::Value) -> io::Result<()> {
+        writeln!(self.stdin, "{}", message)?;
+        self.stdin.flush()
+    }
+
+    fn ask(
+        &mut self,
+        request: Request,
+        state: &PlayerGameState,
+        auction: Option<&Auction>,
+    ) -> Response {
+        if let Err(error) = self.send(request_message(&request, state, auction)) {
+            panic!("Bot can't be reached: {}", error);
+        }
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let reply = loop {
+            let reply = match deadline {
+                Some(deadline) => self
+                    .replies
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .replies
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match reply {
+                // the answer to a request that already timed out
+                Ok(_) if self.late > 0 => self.late -= 1,
+                Ok(reply) => break reply,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.late += 1;
+                    panic!("Bot didn't answer in time");
+                }
+                Err(RecvTimeoutError::Disconnected) => panic!("Bot exited"),
+            }
+        };
+        match parse_response(&reply, &request) {
+            Ok(response) if request.allows(&response) => response,
+            Ok(response) => panic!("Bot answered {:?}, which isn't allowed", response),
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
+
+impl Agent for SubprocessAgent {
+    fn get_intent(&mut self, state: &PlayerGameState, auction: &Auction) -> bool {
+        let request = Request::Intent {
+            player: state.player_nr,
+        };
+        match self.ask(request, state, Some(auction)) {
+            Response::Intent(intent) => intent,
+            _ => unreachable!("responses are checked against the request"),
+        }
+    }
+
+    fn get_bid(&mut self, state: &PlayerGameState, auction: &Auction) -> Contract {
+        let request = Request::Bid {
+            player: state.player_nr,
+            valid_bids: auction.valid_bids(Some(state.hand)),
+        };
+        match self.ask(request, state, Some(auction)) {
+            Response::Bid(bid) => bid,
+            _ => unreachable!("responses are checked against the request"),
+        }
+    }
+
+    fn get_play(&mut self, state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+        let request = Request::Play {
+            player: state.player_nr,
+            legal_plays: legal_plays.into_iter().copied().collect(),
+        };
+        match self.ask(request, state, None) {
+            Response::Play(card) => card,
+            _ => unreachable!("responses are checked against the request"),
+        }
+    }
+
+    fn game_over(&mut self, player_nr: usize, auction: &Auction, game: &Game) {
+        // nothing to answer, and a bot that went away shows up at the next decision
+        let _ = self.send(game_over_message(player_nr, auction, game));
+    }
+}
+
+impl Drop for SubprocessAgent {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::agent::RandomAgent;
+    use crate::schafkopf_env::match_logic::Match;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    /// Never plays a contract and always plays the first legal card.
+    const BOT: &str = r#"
+        while read -r line; do
+            case "$line" in
+                *'"type":"intent"'*) echo '{"intent":false}' ;;
+                *'"type":"play"'*)
+                    card=$(echo "$line" | sed 's/.*"legal_plays":\["\([^"]*\)".*/\1/')
+                    echo "{\"card\":\"$card\"}" ;;
+            esac
+        done
+    "#;
+
+    fn bot(script: &str) -> SubprocessAgent {
+        SubprocessAgent::spawn(Command::new("sh").arg("-c").arg(script)).unwrap()
+    }
+
+    #[test]
+    fn test_subprocess_plays_a_match() {
+        let mut m = Match::new();
+        m.register_player(Box::new(bot(BOT))).unwrap();
+        for seed in 1..4 {
+            m.register_player(Box::new(RandomAgent::with_seed(seed)))
+                .unwrap();
+        }
+        let results = m.play(3).unwrap();
+        for result in results {
+            assert!(result.faults.is_empty(), "{:?}", result.faults);
+        }
+    }
+
+    #[test]
+    fn test_misbehaving_subprocess_faults() {
+        let mut m = Match::new();
+        let mut silent = bot("sleep 5");
+        silent.timeout = Some(Duration::from_millis(50));
+        m.register_player(Box::new(silent)).unwrap();
+        m.register_player(Box::new(bot("echo nonsense"))).unwrap();
+        m.register_player(Box::new(bot("exit 0"))).unwrap();
+        m.register_player(Box::new(bot(BOT))).unwrap();
+        let result = m.play_game().unwrap();
+        let faulty = result
+            .faults
+            .iter()
+            .map(|f| f.player)
+            .collect::<Vec<usize>>();
+        for player in 0..3 {
+            assert!(faulty.contains(&player));
+        }
+        assert!(!faulty.contains(&3));
+    }
+
+    #[test]
+    fn test_late_answers_are_skipped() {
+        // answers the first request only after it timed out
+        let mut slow = bot(&format!(
+            "read -r line; sleep 0.3; echo '{{\"intent\":false}}'; {}",
+            BOT
+        ));
+        slow.timeout = Some(Duration::from_millis(100));
+        let game = Game::new(0);
+        let state = game.get_player_game_state(0);
+        let auction = Auction::new(0);
+        let timed_out = catch_unwind(AssertUnwindSafe(|| slow.get_intent(&state, &auction)));
+        assert!(timed_out.is_err());
+
+        // once before and once after the late answer arrived
+        slow.timeout = Some(Duration::from_secs(5));
+        assert!(!slow.get_intent(&state, &auction));
+        let hand = state.hand.cards.iter().collect::<Vec<&Card>>();
+        assert_eq!(slow.get_play(&state, hand), state.hand.cards[0]);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::schafkopf_env::agent::Agent;
+use crate::schafkopf_env::game_logic::{Card, RuleSet};
+use crate::schafkopf_env::match_logic::{HandResult, Match, MatchError};
+use crate::schafkopf_env::rating::Ratings;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Creates a fresh agent for every seat an entrant takes.
+pub type AgentFactory = Box<dyn Fn() -> Box<dyn Agent>>;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TournamentConfig {
+    /// Number of deals every lineup plays.
+    pub deals: usize,
+    /// Seed the deals are shuffled with, the same seed gives the same deals.
+    pub seed: u64,
+    /// Replays every deal with the lineup rotated through all four seats,
+    /// so every entrant gets to play every hand.
+    pub duplicate: bool,
+    pub rules: RuleSet,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> TournamentConfig {
+        TournamentConfig {
+            deals: 100,
+            seed: 0,
+            duplicate: true,
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+/// How an entrant did over the whole tournament.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub hands: usize,
+    pub total: i64,
+    /// Mean earnings per hand.
+    pub mean: f64,
+    /// Half-width of the 95% confidence interval of the mean.
+    pub confidence: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TournamentResult {
+    /// Best mean first.
+    pub standings: Vec<Standing>,
+    pub hands: usize,
+}
+
+impl fmt::Display for TournamentResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} hands", self.hands)?;
+        for (rank, standing) in self.standings.iter().enumerate() {
+            writeln!(
+                f,
+                "{}. {}: {:+.2} ± {:.2} per hand ({} hands, {:+} total)",
+                rank + 1,
+                standing.name,
+                standing.mean,
+                standing.confidence,
+                standing.hands,
+                standing.total
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Entrant {
+    name: String,
+    factory: AgentFactory,
+}
+
+/// Pits agents against each other over many seeded deals.
+///
+/// Every entrant leads a lineup of four, filled up with the entrants after it. In the
+/// duplicate format each deal is replayed with the lineup rotated through every seat, and
+/// the results of an entrant are taken per deal, which cancels out most of the card luck.
+pub struct Tournament {
+    pub config: TournamentConfig,
+    entrants: Vec<Entrant>,
+}
+
+impl Tournament {
+    pub fn new(config: TournamentConfig) -> Tournament {
+        Tournament {
+            config,
+            entrants: Vec::new(),
+        }
+    }
+
+    pub fn add_entrant(&mut self, name: &str, factory: AgentFactory) {
+        self.entrants.push(Entrant {
+            name: name.to_string(),
+            factory,
+        });
+    }
+
+    /// The deals every lineup plays, in order.
+    pub fn deals(&self) -> Vec<[Vec<Card>; 4]> {
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        (0..self.config.deals)
+            .map(|_| {
+                let mut cards = Card::all();
+                cards.shuffle(&mut rng);
+                [0, 1, 2, 3].map(|p| cards[p * 8..p * 8 + 8].to_vec())
+            })
+            .collect()
+    }
+
+    pub fn run(&self) -> Result<TournamentResult, MatchError> {
+        self.run_with(|_, _| {})
+    }
+
+    /// Runs the tournament and rates every hand under the entrants' names.
+    pub fn run_rated(&self, ratings: &mut Ratings) -> Result<TournamentResult, MatchError> {
+        self.run_with(|names, result| ratings.update(names, result))
+    }
+
+    fn run_with<F>(&self, mut on_hand: F) -> Result<TournamentResult, MatchError>
+    where
+        F: FnMut([&str; 4], &HandResult),
+    {
+        let count = self.entrants.len();
+        if count == 0 {
+            return Ok(TournamentResult::default());
+        }
+        let deals = self.deals();
+        let rotations = if self.config.duplicate { 4 } else { 1 };
+        // earnings of every entrant, summed per lineup and deal
+        let mut samples: Vec<Vec<f64>> = vec![Vec::new(); count];
+        let mut hands = vec![0; count];
+        let mut totals = vec![0i64; count];
+        let mut played = 0;
+
+        for first in 0..count {
+            let lineup = [0, 1, 2, 3].map(|k| (first + k) % count);
+            let mut per_deal = vec![vec![(0i64, 0usize); deals.len()]; count];
+            for rotation in 0..rotations {
+                let seats = [0, 1, 2, 3].map(|seat| lineup[(seat + rotation) % 4]);
+                let mut table = Match::with_rules(self.config.rules);
+                for entrant in seats.iter() {
+                    table.register_player((self.entrants[*entrant].factory)())?;
+                }
+                for (deal_nr, deal) in deals.iter().enumerate() {
+                    let result = table.play_deal(deal.clone())?;
+                    on_hand(seats.map(|e| self.entrants[e].name.as_str()), &result);
+                    played += 1;
+                    for (seat, entrant) in seats.iter().enumerate() {
+                        let earning = result.earnings[seat] as i64;
+                        per_deal[*entrant][deal_nr].0 += earning;
+                        per_deal[*entrant][deal_nr].1 += 1;
+                        totals[*entrant] += earning;
+                        hands[*entrant] += 1;
+                    }
+                }
+            }
+            for (entrant, deals) in per_deal.into_iter().enumerate() {
+                for (total, seats) in deals.into_iter().filter(|d| d.1 > 0) {
+                    samples[entrant].push(total as f64 / seats as f64);
+                }
+            }
+        }
+
+        let mut standings = self
+            .entrants
+            .iter()
+            .enumerate()
+            .map(|(i, entrant)| {
+                let (mean, confidence) = mean_confidence(&samples[i]);
+                Standing {
+                    name: entrant.name.clone(),
+                    hands: hands[i],
+                    total: totals[i],
+                    mean,
+                    confidence,
+                }
+            })
+            .collect::<Vec<Standing>>();
+        standings.sort_by(|a, b| b.mean.total_cmp(&a.mean));
+        Ok(TournamentResult {
+            standings,
+            hands: played,
+        })
+    }
+}
+
+/// Mean and half-width of its 95% confidence interval, using the normal approximation.
+pub fn mean_confidence(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    if samples.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = samples.iter().sum::<f64>() / n;
+    if samples.len() < 2 {
+        return (mean, f64::INFINITY);
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, 1.96 * (variance / n).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schafkopf_env::agent::RandomAgent;
+    use crate::schafkopf_env::game_logic::{Auction, Contract, PlayerGameState};
+    use crate::schafkopf_env::rating::INITIAL_RATING;
+
+    /// Always passes and plays the first legal card, so a deal always plays out the same.
+    struct Passive;
+
+    impl Agent for Passive {
+        fn get_intent(&mut self, _state: &PlayerGameState, _auction: &Auction) -> bool {
+            false
+        }
+
+        fn get_bid(&mut self, _state: &PlayerGameState, _auction: &Auction) -> Contract {
+            Contract::None
+        }
+
+        fn get_play(&mut self, _state: &PlayerGameState, legal_plays: Vec<&Card>) -> Card {
+            *legal_plays[0]
+        }
+    }
+
+    #[test]
+    fn test_duplicate_cancels_card_luck() {
+        let config = TournamentConfig {
+            deals: 10,
+            seed: 3,
+            ..TournamentConfig::default()
+        };
+        let mut tournament = Tournament::new(config);
+        for name in ["a", "b", "c", "d"] {
+            tournament.add_entrant(name, Box::new(|| Box::new(Passive)));
+        }
+        let result = tournament.run().unwrap();
+        assert_eq!(result.hands, 4 * 4 * 10);
+        for standing in result.standings.iter() {
+            assert_eq!(standing.hands, 4 * 4 * 10);
+            assert_eq!(standing.total, 0);
+            assert_eq!(standing.confidence, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_tournament_reports_every_entrant() {
+        let config = TournamentConfig {
+            deals: 6,
+            duplicate: false,
+            ..TournamentConfig::default()
+        };
+        let mut tournament = Tournament::new(config);
+        tournament.add_entrant("random", Box::new(|| Box::new(RandomAgent::new())));
+        tournament.add_entrant("passive", Box::new(|| Box::new(Passive)));
+        assert_eq!(tournament.deals()[0], tournament.deals()[0]);
+
+        let result = tournament.run().unwrap();
+        assert_eq!(result.standings.len(), 2);
+        assert_eq!(result.hands, 2 * 6);
+        assert_eq!(result.standings.iter().map(|s| s.total).sum::<i64>(), 0);
+        assert!(result.standings.windows(2).all(|w| w[0].mean >= w[1].mean));
+        assert!(result.to_string().contains("passive"));
+    }
+
+    #[test]
+    fn test_tournament_updates_ratings() {
+        let config = TournamentConfig {
+            deals: 5,
+            ..TournamentConfig::default()
+        };
+        let mut tournament = Tournament::new(config);
+        tournament.add_entrant("random", Box::new(|| Box::new(RandomAgent::new())));
+        tournament.add_entrant("passive", Box::new(|| Box::new(Passive)));
+        let mut ratings = Ratings::new();
+        // with two entrants both sit at the table twice, rated once per hand
+        let result = tournament.run_rated(&mut ratings).unwrap();
+        assert_eq!(ratings.leaderboard().len(), 2);
+        for standing in result.standings.iter() {
+            assert_eq!(ratings.get(&standing.name).hands as usize, result.hands);
+            assert_eq!(standing.hands, 2 * result.hands);
+        }
+        let total = ratings
+            .leaderboard()
+            .iter()
+            .map(|(_, r)| r.rating)
+            .sum::<f64>();
+        assert!((total - 2.0 * INITIAL_RATING).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mean_confidence() {
+        assert_eq!(mean_confidence(&[]), (0.0, 0.0));
+        let (mean, confidence) = mean_confidence(&[1.0, 3.0, 1.0, 3.0]);
+        assert_eq!(mean, 2.0);
+        assert!((confidence - 1.96 * (4.0f64 / 3.0 / 4.0).sqrt()).abs() < 1e-12);
+    }
+}